@@ -0,0 +1,38 @@
+//! Tweets saved to read later, kept on disk rather than with Twitter so that
+//! they outlive the tweets themselves.
+//!
+//! They're written the same way as the cache, so each one keeps its id,
+//! author, text and timestamp, along with everything else needed to show it
+//! like any other tweet.
+
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use egg_mode::tweet::Tweet;
+
+use crate::{cache, Error, Result};
+
+/// Where bookmarks are kept. They're shared between accounts.
+pub fn path() -> Option<PathBuf> {
+    let dir = match dirs::data_dir() {
+        Some(dir) => dir.join("twrs"),
+        None => dirs::home_dir()?.join(".config").join("twrs"),
+    };
+    Some(dir.join("bookmarks.json"))
+}
+
+/// Reads the bookmarks saved to `path`. There being no file yet just means
+/// there aren't any.
+pub fn load(path: &Path) -> Result<Vec<Tweet>> {
+    match cache::read(path) {
+        Err(Error::Io(e)) if e.kind() == ErrorKind::NotFound => Ok(vec![]),
+        result => result,
+    }
+}
+
+/// Writes `tweets` to `path`, replacing whatever was there.
+pub fn save<'a, I: IntoIterator<Item = &'a Tweet>>(path: &Path, tweets: I) -> Result<()> {
+    cache::save(path, tweets)
+}
@@ -0,0 +1,318 @@
+//! Keeps the home timeline on disk between runs, so there's something to show
+//! before the first fetch comes back.
+//!
+//! egg-mode can read tweets but not write them, so they're written out in the
+//! same JSON Twitter sends, which egg-mode then reads back in like any other
+//! response. The few fields twrs never looks at, like places, are left out.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use egg_mode::{
+    entities::{
+        HashtagEntity, MediaEntity, MediaSize, MediaType, MentionEntity, ResizeMode, UrlEntity,
+    },
+    tweet::Tweet,
+    user::{TwitterUser, UserEntityDetail},
+};
+use serde_json::{json, Value};
+
+use crate::Result;
+
+/// Cached tweets older than this are dropped when the cache is loaded, rather
+/// than being shown as if they were current.
+const MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The format Twitter writes timestamps in.
+const TIMESTAMP_FORMAT: &str = "%a %b %d %T %z %Y";
+
+/// Where the home timeline of the account with `token` is cached.
+///
+/// Access tokens start with the id of the user they belong to, which keeps
+/// each account's cache separate without having to look the user up first.
+pub fn path(token: &egg_mode::Token) -> Option<PathBuf> {
+    let user = match token {
+        egg_mode::Token::Access { access, .. } => access.key.split('-').next()?.to_string(),
+        egg_mode::Token::Bearer(_) => return None,
+    };
+    let dir = match dirs::cache_dir() {
+        Some(dir) => dir.join("twrs"),
+        None => dirs::home_dir()?.join(".config").join("twrs").join("cache"),
+    };
+    Some(dir.join(format!("home-{}.json", user)))
+}
+
+/// Reads back the tweets saved to `path`, leaving out any older than
+/// `MAX_AGE`.
+pub fn load(path: &Path, now: DateTime<Utc>) -> Result<Vec<Tweet>> {
+    let max_age = chrono::Duration::from_std(MAX_AGE).unwrap();
+    Ok(read(path)?
+        .into_iter()
+        .filter(|tweet| now.signed_duration_since(tweet.created_at) < max_age)
+        .collect())
+}
+
+/// Reads back all the tweets saved to `path`, however old.
+pub fn read(path: &Path) -> Result<Vec<Tweet>> {
+    let tweets: BTreeMap<u64, Tweet> = serde_json::from_slice(&std::fs::read(path)?)?;
+    Ok(tweets.into_values().collect())
+}
+
+/// Writes `tweets` to `path`, keyed by id, replacing whatever was there.
+pub fn save<'a, I: IntoIterator<Item = &'a Tweet>>(path: &Path, tweets: I) -> Result<()> {
+    let tweets: BTreeMap<u64, Value> = tweets
+        .into_iter()
+        .map(|tweet| (tweet.id, tweet_json(tweet)))
+        .collect();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serde_json::to_vec(&tweets)?)?;
+    Ok(())
+}
+
+/// Where the position in the timeline cached at `path` is kept, which is the
+/// id of the tweet that was selected when it was saved.
+fn position_path(path: &Path) -> PathBuf {
+    path.with_extension("position")
+}
+
+/// Reads back the position saved alongside the cache at `path`, if there is
+/// one.
+pub fn load_position(path: &Path) -> Option<u64> {
+    std::fs::read_to_string(position_path(path))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Saves `id` as the position in the timeline cached at `path`.
+pub fn save_position(path: &Path, id: u64) -> Result<()> {
+    std::fs::write(position_path(path), id.to_string())?;
+    Ok(())
+}
+
+/// Where the newest tweet read in the timeline cached at `path` is kept.
+fn read_path(path: &Path) -> PathBuf {
+    path.with_extension("read")
+}
+
+/// Reads back the newest tweet read in the timeline cached at `path`, if
+/// that's been saved.
+pub fn load_read(path: &Path) -> Option<u64> {
+    std::fs::read_to_string(read_path(path))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Saves `id` as the newest tweet read in the timeline cached at `path`.
+pub fn save_read(path: &Path, id: u64) -> Result<()> {
+    std::fs::write(read_path(path), id.to_string())?;
+    Ok(())
+}
+
+/// `tweet` as Twitter would send it, near enough for egg-mode to read back.
+pub fn tweet_json(tweet: &Tweet) -> Value {
+    let text = &tweet.text;
+    json!({
+        "coordinates": tweet.coordinates.map(|c| json!({
+            "type": "Point",
+            "coordinates": [c.0, c.1],
+        })),
+        "created_at": tweet.created_at.format(TIMESTAMP_FORMAT).to_string(),
+        "current_user_retweet": tweet.current_user_retweet.map(|id| json!({ "id": id })),
+        "display_text_range": tweet.display_text_range.map(|r| codepoints(r, text)),
+        "entities": {
+            "hashtags": hashtags_json(&tweet.entities.hashtags, text),
+            "symbols": hashtags_json(&tweet.entities.symbols, text),
+            "urls": urls_json(&tweet.entities.urls, text),
+            "user_mentions": mentions_json(&tweet.entities.user_mentions, text),
+            "media": tweet.entities.media.as_ref().map(|media| media_json(media, text)),
+        },
+        "extended_entities": tweet.extended_entities.as_ref().map(|entities| json!({
+            "media": media_json(&entities.media, text),
+        })),
+        "favorite_count": tweet.favorite_count,
+        "favorited": tweet.favorited,
+        "id": tweet.id,
+        "in_reply_to_user_id": tweet.in_reply_to_user_id,
+        "in_reply_to_screen_name": tweet.in_reply_to_screen_name,
+        "in_reply_to_status_id": tweet.in_reply_to_status_id,
+        "lang": tweet.lang,
+        "possibly_sensitive": tweet.possibly_sensitive,
+        "quoted_status_id": tweet.quoted_status_id,
+        "quoted_status": tweet.quoted_status.as_deref().map(tweet_json),
+        "retweet_count": tweet.retweet_count,
+        "retweeted": tweet.retweeted,
+        "retweeted_status": tweet.retweeted_status.as_deref().map(tweet_json),
+        "source": tweet.source.as_ref().map(|source| format!(
+            "<a href=\"{}\" rel=\"nofollow\">{}</a>",
+            source.url, source.name
+        )),
+        "full_text": text,
+        "truncated": tweet.truncated,
+        "user": tweet.user.as_deref().map(user_json),
+        "withheld_copyright": tweet.withheld_copyright,
+        "withheld_in_countries": tweet.withheld_in_countries,
+        "withheld_scope": tweet.withheld_scope,
+    })
+}
+
+fn user_json(user: &TwitterUser) -> Value {
+    let description = user.description.as_deref().unwrap_or("");
+    let url = user.url.as_deref().unwrap_or("");
+    json!({
+        "contributors_enabled": user.contributors_enabled,
+        "created_at": user.created_at.format(TIMESTAMP_FORMAT).to_string(),
+        "default_profile": user.default_profile,
+        "default_profile_image": user.default_profile_image,
+        "description": user.description,
+        "entities": {
+            "description": entity_detail_json(&user.entities.description, description),
+            "url": user.entities.url.as_ref().map(|detail| entity_detail_json(detail, url)),
+        },
+        "favourites_count": user.favourites_count,
+        "follow_request_sent": user.follow_request_sent,
+        "followers_count": user.followers_count,
+        "friends_count": user.friends_count,
+        "geo_enabled": user.geo_enabled,
+        "id": user.id,
+        "is_translator": user.is_translator,
+        "lang": user.lang,
+        "listed_count": user.listed_count,
+        "location": user.location,
+        "name": user.name,
+        "profile_background_color": user.profile_background_color,
+        "profile_background_image_url": user.profile_background_image_url,
+        "profile_background_image_url_https": user.profile_background_image_url_https,
+        "profile_background_tile": user.profile_background_tile,
+        "profile_banner_url": user.profile_banner_url,
+        "profile_image_url": user.profile_image_url,
+        "profile_image_url_https": user.profile_image_url_https,
+        "profile_link_color": user.profile_link_color,
+        "profile_sidebar_border_color": user.profile_sidebar_border_color,
+        "profile_sidebar_fill_color": user.profile_sidebar_fill_color,
+        "profile_text_color": user.profile_text_color,
+        "profile_use_background_image": user.profile_use_background_image,
+        "protected": user.protected,
+        "screen_name": user.screen_name,
+        "show_all_inline_media": user.show_all_inline_media,
+        "statuses_count": user.statuses_count,
+        "time_zone": user.time_zone,
+        "url": user.url,
+        "utc_offset": user.utc_offset,
+        "verified": user.verified,
+        "withheld_in_countries": user.withheld_in_countries,
+        "withheld_scope": user.withheld_scope,
+    })
+}
+
+fn entity_detail_json(detail: &UserEntityDetail, text: &str) -> Value {
+    json!({ "urls": urls_json(&detail.urls, text) })
+}
+
+fn hashtags_json(hashtags: &[HashtagEntity], text: &str) -> Value {
+    hashtags
+        .iter()
+        .map(|hashtag| {
+            json!({
+                "indices": codepoints(hashtag.range, text),
+                "text": hashtag.text,
+            })
+        })
+        .collect()
+}
+
+fn urls_json(urls: &[UrlEntity], text: &str) -> Value {
+    urls.iter()
+        .map(|url| {
+            json!({
+                "display_url": url.display_url,
+                "expanded_url": url.expanded_url,
+                "indices": codepoints(url.range, text),
+                "url": url.url,
+            })
+        })
+        .collect()
+}
+
+fn mentions_json(mentions: &[MentionEntity], text: &str) -> Value {
+    mentions
+        .iter()
+        .map(|mention| {
+            json!({
+                "id": mention.id,
+                "indices": codepoints(mention.range, text),
+                "name": mention.name,
+                "screen_name": mention.screen_name,
+            })
+        })
+        .collect()
+}
+
+fn media_json(media: &[MediaEntity], text: &str) -> Value {
+    media
+        .iter()
+        .map(|media| {
+            json!({
+                "display_url": media.display_url,
+                "expanded_url": media.expanded_url,
+                "id": media.id,
+                "indices": codepoints(media.range, text),
+                "media_url": media.media_url,
+                "media_url_https": media.media_url_https,
+                "sizes": {
+                    "thumb": size_json(&media.sizes.thumb),
+                    "small": size_json(&media.sizes.small),
+                    "medium": size_json(&media.sizes.medium),
+                    "large": size_json(&media.sizes.large),
+                },
+                "source_status_id": media.source_status_id,
+                "type": match media.media_type {
+                    MediaType::Photo => "photo",
+                    MediaType::Video => "video",
+                    MediaType::Gif => "animated_gif",
+                },
+                "url": media.url,
+                "video_info": media.video_info.as_ref().map(|info| json!({
+                    "aspect_ratio": info.aspect_ratio,
+                    "duration_millis": info.duration_millis,
+                    "variants": info.variants.iter().map(|variant| json!({
+                        "bitrate": variant.bitrate,
+                        "content_type": variant.content_type.to_string(),
+                        "url": variant.url,
+                    })).collect::<Vec<_>>(),
+                })),
+                "ext_alt_text": media.ext_alt_text,
+            })
+        })
+        .collect()
+}
+
+fn size_json(size: &MediaSize) -> Value {
+    json!({
+        "w": size.w,
+        "h": size.h,
+        "resize": match size.resize {
+            ResizeMode::Fit => "fit",
+            ResizeMode::Crop => "crop",
+        },
+    })
+}
+
+/// egg-mode turns Twitter's entity indices, which count characters, into byte
+/// offsets. This turns them back so it can do that again on load.
+fn codepoints((start, end): (usize, usize), text: &str) -> (usize, usize) {
+    let count = |offset: usize| match text.get(..offset) {
+        Some(prefix) => prefix.chars().count(),
+        None => offset,
+    };
+    (count(start), count(end))
+}
@@ -0,0 +1,125 @@
+//! Writes out the tweets loaded in a timeline, for archiving them or looking
+//! through them with something else.
+//!
+//! JSON exports are a list of tweets in the JSON Twitter sends, written the
+//! same way as the cache, so `--mock` can play them back. CSV exports have a
+//! row for each tweet with just the basics.
+
+use std::path::{Path, PathBuf};
+
+use egg_mode::tweet::Tweet;
+
+use crate::{cache, Result};
+
+/// What to write an export as.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Csv,
+}
+
+impl Format {
+    /// The format to export to `path` as, going by its extension.
+    pub fn of(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "json" => Some(Format::Json),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// The path typed in as `input`, with a leading `~` standing for the home
+/// directory the way it would in a shell.
+pub fn expand(input: &str) -> PathBuf {
+    match (input.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(input),
+    }
+}
+
+/// Writes `tweets` to `path` as `format`, in the order they're given,
+/// replacing whatever was there.
+pub fn save<'a, I: IntoIterator<Item = &'a Tweet>>(
+    path: &Path,
+    format: Format,
+    tweets: I,
+) -> Result<()> {
+    let contents = match format {
+        Format::Json => {
+            let tweets: Vec<_> = tweets.into_iter().map(cache::tweet_json).collect();
+            serde_json::to_vec_pretty(&tweets)?
+        }
+        Format::Csv => csv(tweets).into_bytes(),
+    };
+    std::fs::write(path, contents)?;
+    Ok(())
+}
+
+fn csv<'a, I: IntoIterator<Item = &'a Tweet>>(tweets: I) -> String {
+    let mut csv = String::from("id,created_at,author,text,like_count,rt_count\r\n");
+    for tweet in tweets {
+        // A retweet's own text is cut off to fit 140 characters, so the
+        // original's is written out in full after who it's by.
+        let text = match &tweet.retweeted_status {
+            Some(original) => format!("RT @{}: {}", author(original), original.text),
+            None => tweet.text.clone(),
+        };
+        let row = [
+            tweet.id.to_string(),
+            tweet.created_at.to_rfc3339(),
+            csv_field(author(tweet)),
+            csv_field(&text),
+            tweet.favorite_count.to_string(),
+            tweet.retweet_count.to_string(),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+/// The screen name of whoever wrote `tweet`, or nothing if Twitter left them
+/// out.
+fn author(tweet: &Tweet) -> &str {
+    tweet
+        .user
+        .as_ref()
+        .map_or("", |user| user.screen_name.as_str())
+}
+
+/// `field` quoted, if it needs to be, so commas, quotes and line breaks in it
+/// don't split it up.
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{tweet_json, user};
+    use serde_json::json;
+
+    #[test]
+    fn retweets_are_written_with_the_whole_of_the_original() {
+        let long = "a long tweet, ".repeat(15);
+        let original = tweet_json(json!({ "full_text": long, "user": user("author") }));
+        let retweet = tweet_json(json!({
+            "id": 2,
+            "full_text": "RT @author: a long tweet, a long tweet, a long t…",
+            "user": user("retweeter"),
+            "retweeted_status": original,
+        }));
+        let retweet: Tweet = serde_json::from_value(retweet).unwrap();
+
+        let csv = csv(vec![&retweet]);
+        let row = csv.lines().nth(1).unwrap();
+        let expected = format!("retweeter,\"RT @author: {}\",0,0", long);
+        assert!(row.ends_with(&expected), "{}", row);
+    }
+}
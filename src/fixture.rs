@@ -0,0 +1,91 @@
+//! Tweets read from a file rather than fetched, for running twrs with
+//! `--mock` to work on the UI without a Twitter account.
+//!
+//! A fixture is either a JSON list of tweets, as Twitter's timeline endpoints
+//! send them, or a cache file written by `cache::save`, which is the easiest
+//! way to get hold of some realistic ones.
+
+use std::{collections::BTreeMap, future, path::Path};
+
+use chrono::Utc;
+use egg_mode::{tweet::Tweet, RateLimit};
+
+use crate::{Error, Page, Result, TimelineSource};
+
+/// How many calls the rate limit claims are left. Fixtures never run out, so
+/// this only needs to be big enough not to slow polling down.
+const CALLS: i32 = 900;
+
+/// Reads the tweets in the fixture at `path`, newest first.
+pub fn load(path: &Path) -> Result<Vec<Tweet>> {
+    let bytes = std::fs::read(path)?;
+    let mut tweets = match serde_json::from_slice::<Vec<Tweet>>(&bytes) {
+        Ok(tweets) => tweets,
+        // If it isn't a cache file either, it was most likely meant to be a
+        // list, so that's the error worth showing.
+        Err(e) => serde_json::from_slice::<BTreeMap<u64, Tweet>>(&bytes)
+            .map_err(|_| Error::Fixture(e))?
+            .into_values()
+            .collect(),
+    };
+    tweets.sort_by_key(|tweet| std::cmp::Reverse(tweet.id));
+    Ok(tweets)
+}
+
+/// Plays back a fixture as if it were a timeline. The first fetch gets all of
+/// it, and every fetch after that gets one of its tweets again as if it had
+/// just been posted, going round them in turn, so there's always something
+/// new to show.
+pub struct Playback {
+    tweets: Vec<Tweet>,
+    started: bool,
+    next: usize,
+    newest: u64,
+}
+
+impl Playback {
+    pub fn new(tweets: Vec<Tweet>) -> Self {
+        Playback {
+            newest: tweets.iter().map(|tweet| tweet.id).max().unwrap_or(0),
+            tweets,
+            started: false,
+            next: 0,
+        }
+    }
+}
+
+impl TimelineSource for Playback {
+    fn newer(&mut self) -> Page<'_> {
+        let tweets = if !self.started {
+            self.started = true;
+            self.tweets.clone()
+        } else if self.tweets.is_empty() {
+            vec![]
+        } else {
+            let mut tweet = self.tweets[self.next % self.tweets.len()].clone();
+            self.next += 1;
+            self.newest += 1;
+            tweet.id = self.newest;
+            tweet.created_at = Utc::now();
+            vec![tweet]
+        };
+        Box::pin(future::ready(Ok(response(tweets))))
+    }
+
+    /// Everything in the fixture comes in the first page, so there's never
+    /// anything older.
+    fn older(&mut self, _oldest: u64) -> Page<'_> {
+        Box::pin(future::ready(Ok(response(vec![]))))
+    }
+}
+
+fn response(tweets: Vec<Tweet>) -> egg_mode::Response<Vec<Tweet>> {
+    egg_mode::Response {
+        rate_limit_status: RateLimit {
+            limit: CALLS,
+            remaining: CALLS,
+            reset: (Utc::now().timestamp() + 15 * 60) as i32,
+        },
+        response: tweets,
+    }
+}
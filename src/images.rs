@@ -0,0 +1,162 @@
+//! Thumbnails of tweets' media, drawn inline on terminals that support the
+//! kitty or iTerm2 graphics protocols.
+//!
+//! tui only knows about text, so thumbnails are written straight to the
+//! terminal after each frame, over a part of the screen left blank for them.
+//! Twitter will convert media to PNG on request, which both protocols can
+//! show as it is, so nothing needs decoding here. Sixel would need the pixels
+//! themselves, so terminals that only support that get the usual indicator.
+
+use std::path::{Path, PathBuf};
+
+use egg_mode::entities::MediaEntity;
+
+use crate::Result;
+
+/// The most base64 the kitty protocol takes in one escape sequence.
+const KITTY_CHUNK: usize = 4096;
+
+/// How much taller a cell is than it is wide, near enough, for working out
+/// how many cells a thumbnail covers.
+const CELL_ASPECT: f64 = 2.0;
+
+/// How thumbnails are sent to the terminal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    Kitty,
+    Iterm,
+}
+
+/// Which protocol the terminal we're running in understands, going by what
+/// terminals say about themselves in the environment, if any.
+pub fn detect() -> Option<Protocol> {
+    let var = |name| std::env::var(name).unwrap_or_default();
+    if var("TERM") == "xterm-kitty"
+        || std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || var("TERM_PROGRAM") == "ghostty"
+    {
+        Some(Protocol::Kitty)
+    } else if ["iTerm.app", "WezTerm"].contains(&var("TERM_PROGRAM").as_str()) {
+        Some(Protocol::Iterm)
+    } else {
+        None
+    }
+}
+
+/// Where to fetch a small PNG of `media` from. Videos and GIFs have a still
+/// of their own at the same address as a photo would be.
+pub fn thumbnail_url(media: &MediaEntity) -> String {
+    let url = &media.media_url_https;
+    let base = match url.rfind('.') {
+        Some(dot) if dot > url.rfind('/').unwrap_or(0) => &url[..dot],
+        _ => url,
+    };
+    format!("{}?format=png&name=small", base)
+}
+
+/// Where the thumbnail fetched from `url` is cached.
+fn path(url: &str) -> Option<PathBuf> {
+    let name = url.split('?').next()?.rsplit('/').next()?;
+    Some(
+        dirs::cache_dir()?
+            .join("twrs")
+            .join("images")
+            .join(name)
+            .with_extension("png"),
+    )
+}
+
+/// The thumbnail at `url`, from the cache if it's been fetched before.
+pub async fn fetch(url: String, token: egg_mode::Token) -> Result<Vec<u8>> {
+    let path = path(&url);
+    if let Some(png) = path.as_deref().and_then(|path| std::fs::read(path).ok()) {
+        return Ok(png);
+    }
+    let request = egg_mode::raw::request_get(&url, &token, None);
+    let (_, png) = egg_mode::raw::response_raw_bytes(request).await?;
+    if let Some(path) = &path {
+        // Failing to cache it only means fetching it again next time.
+        let _ = save(path, &png);
+    }
+    Ok(png)
+}
+
+fn save(path: &Path, png: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, png)?;
+    Ok(())
+}
+
+/// How many columns and rows `png` covers when it's made as big as it can be
+/// within `columns` by `rows` without stretching it.
+pub fn fit(png: &[u8], columns: u16, rows: u16) -> (u16, u16) {
+    let (width, height) = match png_size(png) {
+        Some((width, height)) if width > 0 && height > 0 => (width as f64, height as f64),
+        _ => return (columns, rows),
+    };
+    let rows_for_width = (f64::from(columns) * height / width / CELL_ASPECT).ceil();
+    if rows_for_width <= f64::from(rows) {
+        (columns, (rows_for_width as u16).max(1))
+    } else {
+        let columns_for_height = f64::from(rows) * CELL_ASPECT * width / height;
+        ((columns_for_height as u16).clamp(1, columns), rows)
+    }
+}
+
+/// The width and height PNG files give at the start of their header chunk.
+fn png_size(png: &[u8]) -> Option<(u32, u32)> {
+    if png.get(..8)? != b"\x89PNG\r\n\x1a\n" || png.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let number = |at: usize| -> Option<u32> {
+        let bytes = png.get(at..at + 4)?;
+        Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    Some((number(16)?, number(20)?))
+}
+
+/// The escape sequence that draws `png` at the cursor, scaled to cover
+/// `columns` by `rows` cells.
+pub fn draw(protocol: Protocol, png: &[u8], columns: u16, rows: u16) -> String {
+    let data = base64::encode(png);
+    match protocol {
+        Protocol::Kitty => {
+            // The data is sent in chunks, each saying whether there's more
+            // to come. Asking for no replies keeps them out of the input.
+            let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK).collect();
+            let mut escape = String::new();
+            for (i, chunk) in chunks.iter().enumerate() {
+                let more = (i + 1 < chunks.len()) as u8;
+                let chunk = std::str::from_utf8(chunk).unwrap();
+                if i == 0 {
+                    escape.push_str(&format!(
+                        "\x1b_Ga=T,f=100,q=2,c={},r={},m={};{}\x1b\\",
+                        columns, rows, more, chunk
+                    ));
+                } else {
+                    escape.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+                }
+            }
+            escape
+        }
+        Protocol::Iterm => format!(
+            "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+            png.len(),
+            columns,
+            rows,
+            data
+        ),
+    }
+}
+
+/// The escape sequence that takes down everything `draw` has drawn, for
+/// protocols that keep images apart from the text. iTerm2 draws them into
+/// the cells themselves, so they go once those cells are redrawn.
+pub fn clear(protocol: Protocol) -> Option<&'static str> {
+    match protocol {
+        Protocol::Kitty => Some("\x1b_Ga=d,q=2\x1b\\"),
+        Protocol::Iterm => None,
+    }
+}
@@ -0,0 +1,383 @@
+//! Key bindings, which can be changed in the `[keys]` table of the config.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use termion::event::Key;
+
+use crate::{Error, Result};
+
+/// Something a key in the timeline can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    ScrollDown,
+    ScrollUp,
+    Top,
+    Bottom,
+    NextByAuthor,
+    PreviousByAuthor,
+    NextTab,
+    PreviousTab,
+    Home,
+    Mentions,
+    User,
+    Search,
+    Popular,
+    Compose,
+    Reply,
+    Quote,
+    Like,
+    Retweet,
+    Undo,
+    Open,
+    CopyText,
+    CopyUrl,
+    Export,
+    Thread,
+    SwitchAccount,
+    Mute,
+    Follow,
+    Profile,
+    Bookmark,
+    Bookmarks,
+    Mark,
+    MarkRead,
+    Pin,
+    Delete,
+    Messages,
+    Pause,
+    Command,
+    Help,
+    Back,
+    Quit,
+}
+
+impl Action {
+    /// The action configured by `name`, if there is one.
+    pub fn named(name: &str) -> Option<Action> {
+        ACTIONS
+            .iter()
+            .find(|(_, n, _, _)| *n == name)
+            .map(|(action, _, _, _)| *action)
+    }
+}
+
+/// Every action, with the name it's configured by, the keys it's bound to
+/// by default and what it does, in the order the help lists them.
+pub const ACTIONS: &[(Action, &str, &[&str], &str)] = &[
+    (
+        Action::ScrollDown,
+        "scroll_down",
+        &["j", "down"],
+        "next tweet",
+    ),
+    (
+        Action::ScrollUp,
+        "scroll_up",
+        &["k", "up"],
+        "previous tweet",
+    ),
+    (Action::Top, "top", &["home", "gg"], "newest tweet"),
+    (
+        Action::Bottom,
+        "bottom",
+        &["end", "G"],
+        "oldest tweet loaded",
+    ),
+    (
+        Action::NextByAuthor,
+        "next_by_author",
+        &["]"],
+        "next tweet by the same author",
+    ),
+    (
+        Action::PreviousByAuthor,
+        "previous_by_author",
+        &["["],
+        "previous tweet by the same author",
+    ),
+    (
+        Action::NextTab,
+        "next_tab",
+        &["tab"],
+        "next tab, or the Nth after typing N",
+    ),
+    (
+        Action::PreviousTab,
+        "previous_tab",
+        &["backtab"],
+        "previous tab",
+    ),
+    (Action::Home, "home", &["h"], "home timeline"),
+    (Action::Mentions, "mentions", &["m"], "mentions"),
+    (Action::User, "user", &["u"], "a user's timeline"),
+    (Action::Search, "search", &["/"], "search"),
+    (
+        Action::Popular,
+        "popular",
+        &["p"],
+        "recent or popular search results",
+    ),
+    (Action::Compose, "compose", &["n"], "new tweet"),
+    (Action::Reply, "reply", &["r"], "reply"),
+    (Action::Quote, "quote", &["Q"], "quote tweet"),
+    (Action::Like, "like", &["f"], "like or unlike"),
+    (
+        Action::Retweet,
+        "retweet",
+        &["t"],
+        "retweet or undo retweet",
+    ),
+    (
+        Action::Undo,
+        "undo",
+        &["U"],
+        "undo the last like or retweet",
+    ),
+    (Action::Open, "open", &["o"], "open in browser"),
+    (Action::CopyText, "copy_text", &["y"], "copy the text"),
+    (Action::CopyUrl, "copy_url", &["Y"], "copy the link"),
+    (
+        Action::Export,
+        "export",
+        &["e"],
+        "save the loaded tweets to a .json or .csv file",
+    ),
+    (Action::Thread, "thread", &["enter"], "show the thread"),
+    (
+        Action::SwitchAccount,
+        "switch_account",
+        &["a"],
+        "switch account",
+    ),
+    (Action::Mute, "mute", &["M"], "mute the author"),
+    (
+        Action::Follow,
+        "follow",
+        &["F"],
+        "follow or unfollow the author",
+    ),
+    (Action::Profile, "profile", &["i"], "the author's profile"),
+    (
+        Action::Bookmark,
+        "bookmark",
+        &["b"],
+        "bookmark or unbookmark",
+    ),
+    (Action::Bookmarks, "bookmarks", &["B"], "bookmarks"),
+    (
+        Action::Mark,
+        "mark",
+        &["v"],
+        "mark to like, retweet or open with others",
+    ),
+    (
+        Action::MarkRead,
+        "mark_read",
+        &["R"],
+        "mark everything read",
+    ),
+    (Action::Pin, "pin", &["P"], "pin to the top, or unpin"),
+    (Action::Delete, "delete", &["d"], "delete your tweet"),
+    (Action::Messages, "messages", &["D"], "direct messages"),
+    (
+        Action::Pause,
+        "pause",
+        &["space"],
+        "pause or resume refreshing",
+    ),
+    (
+        Action::Command,
+        "command",
+        &[":"],
+        "run a command by name, like :like or :user rustlang",
+    ),
+    (Action::Help, "help", &["?"], "this help"),
+    (
+        Action::Back,
+        "back",
+        &["esc"],
+        "clear marks, leave the thread, or quit",
+    ),
+    (Action::Quit, "quit", &["q"], "quit"),
+];
+
+/// The keys for one action: either a single key, like `"j"`, or a list, like
+/// `["j", "down"]`. Keys are written as the character they type, or as one of
+/// `enter`, `esc`, `space`, `tab`, `backtab` (shift-tab), `backspace`, `up`,
+/// `down`, `left`, `right`, `home`, `end`, `pageup` or `pagedown`. Two
+/// characters, like `"gg"`, are typed one after the other.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Binding {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Binding {
+    fn keys(&self) -> &[String] {
+        match self {
+            Binding::One(key) => std::slice::from_ref(key),
+            Binding::Many(keys) => keys,
+        }
+    }
+}
+
+/// Which action each key, or sequence of keys, is bound to, once the config
+/// has been applied over the defaults.
+#[derive(Debug)]
+pub struct Keymap {
+    actions: HashMap<Vec<Key>, Action>,
+    keys: HashMap<Action, Vec<Vec<Key>>>,
+}
+
+impl Keymap {
+    /// Binds the actions named in `config` to the keys given for them, and the
+    /// rest to their defaults. It's an error to name an action or a key that
+    /// doesn't exist, to bind one key to two actions, or to bind a key on its
+    /// own as well as at the start of a sequence, since the sequence could
+    /// never be typed.
+    pub fn new(config: &BTreeMap<String, Binding>) -> Result<Keymap> {
+        if let Some(name) = config.keys().find(|name| Action::named(name).is_none()) {
+            return Err(Error::Keys(format!("there's no action called {:?}", name)));
+        }
+
+        let mut keymap = Keymap {
+            actions: HashMap::new(),
+            keys: HashMap::new(),
+        };
+        for (action, name, defaults, _) in ACTIONS {
+            let names: Vec<&str> = match config.get(*name) {
+                Some(binding) => binding.keys().iter().map(String::as_str).collect(),
+                None => defaults.to_vec(),
+            };
+            let mut keys = vec![];
+            for key_name in names {
+                let sequence = parse_keys(key_name).ok_or_else(|| {
+                    Error::Keys(format!("{:?}, bound to {}, isn't a key", key_name, name))
+                })?;
+                let clash = keymap
+                    .actions
+                    .iter()
+                    .find(|(other, _)| other.starts_with(&sequence) || sequence.starts_with(other));
+                if let Some((other_keys, other)) = clash {
+                    let other = ACTIONS.iter().find(|(a, _, _, _)| a == other).unwrap().1;
+                    return Err(Error::Keys(if *other_keys == sequence {
+                        format!("{:?} is bound to both {} and {}", key_name, other, name)
+                    } else {
+                        format!(
+                            "{:?}, bound to {}, and {:?}, bound to {}, start the same way",
+                            key_name,
+                            name,
+                            sequence_name(other_keys),
+                            other
+                        )
+                    }));
+                }
+                keymap.actions.insert(sequence.clone(), *action);
+                keys.push(sequence);
+            }
+            keymap.keys.insert(*action, keys);
+        }
+        Ok(keymap)
+    }
+
+    /// The action the sequence of `keys` is bound to, if any.
+    pub fn action(&self, keys: &[Key]) -> Option<Action> {
+        self.actions.get(keys).copied()
+    }
+
+    /// Whether `keys` is the start of a longer sequence that's bound to
+    /// something, so more keys are needed to tell what to do.
+    pub fn is_prefix(&self, keys: &[Key]) -> bool {
+        self.actions
+            .keys()
+            .any(|sequence| sequence.len() > keys.len() && sequence.starts_with(keys))
+    }
+
+    /// The first key bound to `action`, written the way the help shows it, if
+    /// it's bound at all.
+    pub fn key(&self, action: Action) -> Option<String> {
+        Some(sequence_name(self.keys.get(&action)?.first()?))
+    }
+
+    /// The keys bound to `action`, written the way the help shows them, like
+    /// "j, ↓". This is empty if it's been unbound.
+    pub fn describe(&self, action: Action) -> String {
+        let keys = self.keys.get(&action).map_or(&[][..], Vec::as_slice);
+        keys.iter()
+            .map(|sequence| sequence_name(sequence))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        // None of the defaults clash, so this can't fail.
+        Keymap::new(&BTreeMap::new()).unwrap()
+    }
+}
+
+fn parse_keys(name: &str) -> Option<Vec<Key>> {
+    if let Some(key) = parse_key(name) {
+        return Some(vec![key]);
+    }
+    match name.chars().collect::<Vec<_>>()[..] {
+        [first, second] if !first.is_whitespace() && !second.is_whitespace() => {
+            Some(vec![Key::Char(first), Key::Char(second)])
+        }
+        _ => None,
+    }
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(Key::Char(c));
+    }
+    Some(match name.to_lowercase().as_str() {
+        // termion reads enter as a newline.
+        "enter" => Key::Char('\n'),
+        "esc" => Key::Esc,
+        "space" => Key::Char(' '),
+        "tab" => Key::Char('\t'),
+        // Shift-tab.
+        "backtab" => Key::BackTab,
+        "backspace" => Key::Backspace,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        _ => return None,
+    })
+}
+
+fn sequence_name(keys: &[Key]) -> String {
+    keys.iter().map(|key| key_name(*key)).collect()
+}
+
+fn key_name(key: Key) -> String {
+    match key {
+        Key::Char('\n') => "enter".to_string(),
+        Key::Char(' ') => "space".to_string(),
+        Key::Char('\t') => "tab".to_string(),
+        Key::Char(c) => c.to_string(),
+        Key::BackTab => "shift-tab".to_string(),
+        Key::Esc => "esc".to_string(),
+        Key::Backspace => "backspace".to_string(),
+        Key::Up => "↑".to_string(),
+        Key::Down => "↓".to_string(),
+        Key::Left => "←".to_string(),
+        Key::Right => "→".to_string(),
+        Key::Home => "home".to_string(),
+        Key::End => "end".to_string(),
+        Key::PageUp => "pageup".to_string(),
+        Key::PageDown => "pagedown".to_string(),
+        _ => "?".to_string(),
+    }
+}
@@ -0,0 +1,178 @@
+//! A log of what was sent to Twitter and how it went, for working out what
+//! went wrong after the fact.
+//!
+//! The UI owns the terminal, so the log is written to a file in the cache
+//! directory instead. Which messages make it in is decided the same way as
+//! `RUST_LOG` usually does: a comma-separated list of levels, each of which can
+//! be limited to a target like `twrs=debug`. `RUST_LOG` wins over the `[log]`
+//! level in the config when both are set.
+
+use std::{
+    fmt::{self, Write as _},
+    fs::{File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use chrono::Local;
+use tracing::{
+    field::{Field, Visit},
+    level_filters::LevelFilter,
+    span, Event, Level, Metadata, Subscriber,
+};
+
+use twrs::{Error, Result};
+
+/// Which messages are logged when neither the config nor `RUST_LOG` says.
+const DEFAULT_FILTER: &str = "twrs=warn";
+
+/// Where the log is written.
+pub fn path() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("twrs").join("twrs.log"))
+}
+
+/// Starts logging to the file at `path()`, with the filter from `RUST_LOG`,
+/// or else `level` from the config. Nothing is opened if the filter turns
+/// everything off.
+pub fn init(level: Option<&str>) -> Result<()> {
+    let env = std::env::var("RUST_LOG").ok();
+    let filter = Filter::parse(env.as_deref().or(level).unwrap_or(DEFAULT_FILTER))?;
+    if filter.max() == LevelFilter::OFF {
+        return Ok(());
+    }
+    let path = match path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    // This only fails if something else set a subscriber first, in which
+    // case that one gets the messages instead.
+    let _ = tracing::subscriber::set_global_default(FileSubscriber {
+        file: Mutex::new(file),
+        filter,
+        next_span: AtomicU64::new(1),
+    });
+    Ok(())
+}
+
+/// The levels to log at, for everything or for particular targets.
+struct Filter {
+    /// Each target prefix with the most verbose level logged for it, where
+    /// an empty prefix covers everything.
+    directives: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    fn parse(filter: &str) -> Result<Filter> {
+        let mut directives = vec![];
+        for directive in filter.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let (target, level) = match directive.rfind('=') {
+                Some(at) => (&directive[..at], &directive[at + 1..]),
+                None => ("", directive),
+            };
+            let level = level
+                .parse()
+                .map_err(|_| Error::Log(format!("{:?} isn't a log level", level)))?;
+            directives.push((target.to_string(), level));
+        }
+        // Longer prefixes are more specific, so they're checked first.
+        directives.sort_by_key(|(target, _)| std::cmp::Reverse(target.len()));
+        Ok(Filter { directives })
+    }
+
+    fn enabled(&self, target: &str, level: &Level) -> bool {
+        self.directives
+            .iter()
+            .find(|(prefix, _)| {
+                target.starts_with(prefix.as_str())
+                    && (target.len() == prefix.len()
+                        || prefix.is_empty()
+                        || target[prefix.len()..].starts_with("::"))
+            })
+            .is_some_and(|(_, max)| level <= max)
+    }
+
+    fn max(&self) -> LevelFilter {
+        self.directives
+            .iter()
+            .map(|(_, level)| *level)
+            .max()
+            .unwrap_or(LevelFilter::OFF)
+    }
+}
+
+/// Writes each message on a line of its own, after when it was logged, its
+/// level and where it came from.
+struct FileSubscriber {
+    file: Mutex<File>,
+    filter: Filter,
+    next_span: AtomicU64,
+}
+
+impl Subscriber for FileSubscriber {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.filter.enabled(metadata.target(), metadata.level())
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(self.filter.max())
+    }
+
+    // Only messages are logged, so spans just need telling apart.
+    fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(self.next_span.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let metadata = event.metadata();
+        let mut line = format!(
+            "{} {:5} {}:",
+            Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            metadata.level(),
+            metadata.target()
+        );
+        event.record(&mut Fields(&mut line));
+        line.push('\n');
+        if let Ok(mut file) = self.file.lock() {
+            // There's nowhere to report failing to log.
+            let _ = file.write_all(line.as_bytes());
+        }
+    }
+
+    fn enter(&self, _span: &span::Id) {}
+
+    fn exit(&self, _span: &span::Id) {}
+}
+
+/// Writes out a message's fields, the message itself as it is and the rest
+/// as `name=value`.
+struct Fields<'a>(&'a mut String);
+
+impl Visit for Fields<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let _ = if field.name() == "message" {
+            write!(self.0, " {:?}", value)
+        } else {
+            write!(self.0, " {}={:?}", field.name(), value)
+        };
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            let _ = write!(self.0, " {}", value);
+        } else {
+            self.record_debug(field, &value);
+        }
+    }
+}
@@ -1,192 +1,7449 @@
-use chrono::{DateTime, Utc};
-use std::time::Duration;
-use std::{collections::BTreeMap, io};
-use termion::{input::MouseTerminal, raw::IntoRawMode, screen::AlternateScreen};
+use chrono::{DateTime, TimeZone, Utc};
+use std::time::{Duration, Instant};
+use std::{
+    cell::OnceCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    ffi::OsString,
+    future::Future,
+    io::{self, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    pin::Pin,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+use termion::{
+    cursor,
+    event::{Key, MouseButton, MouseEvent},
+    input::{MouseTerminal, TermRead},
+    raw::{IntoRawMode, RawTerminal},
+    screen::{AlternateScreen, ToMainScreen},
+};
+use tokio::sync::{mpsc, watch, Semaphore};
 use tui::{
-    backend::TermionBackend,
+    backend::{Backend, TermionBackend},
     buffer::Buffer,
-    layout::Rect,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::Modifier,
     style::{Color, Style},
     text::{Span, Spans},
-    widgets::Widget,
-    Terminal,
+    widgets::{Block, Borders, Clear, ListState, Paragraph, StatefulWidget, Tabs, Widget, Wrap},
+    Frame, Terminal,
 };
 
-use dialoguer::Input;
+use dialoguer::{Input, Password, Select};
+use rand::Rng;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use egg_mode::{
-    tweet::{Timeline, Tweet},
-    KeyPair,
-    Token::{Access, Bearer},
+    entities::{MediaEntity, MediaType},
+    search::{ResultType, SearchBuilder},
+    tweet::{DraftTweet, Timeline, Tweet},
+    user::{TwitterUser, UserID},
+    KeyPair, RateLimit, Response,
+};
+
+use twrs::{
+    authenticate, config_path, has_error_code, is_revoked,
+    keys::{Action, Keymap, ACTIONS},
+    load_config, Config, Confirmations, Density, Error, Filters, Log, Network, Notifications,
+    Order, Palette, Result, Tab, Theme, Twitter, Ui, Zone,
 };
-use serde::{Deserialize, Serialize};
-use thiserror::Error;
 
-#[derive(Debug, Error)]
-enum Error {
-    #[error("io error: {0}")]
-    Io(#[from] std::io::Error),
+mod bookmarks;
+mod cache;
+mod export;
+mod fixture;
+mod images;
+mod logging;
+mod messages;
+
+use messages::Conversation;
+
+/// How often the screen is redrawn, independent of how often we fetch.
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The longest the poller waits between retries when it can't reach Twitter.
+const MAX_RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// The most results Twitter gives out in one go from a search.
+const MAX_SEARCH_PAGE_SIZE: u32 = 100;
+
+/// How many tweets short of the oldest loaded the selection gets before the
+/// next page of older ones is fetched in the background.
+const PREFETCH_DISTANCE: usize = 10;
+
+/// The share of the rate limit's calls that fetching ahead of time leaves for
+/// everything else.
+const PREFETCH_RESERVE: f64 = 0.25;
+
+/// The outcome of one fetch by a timeline's poller.
+struct Fetched {
+    fetch: Fetch,
+    tweets: Result<Vec<Tweet>>,
+    rate_limit: Option<RateLimit>,
+}
+
+/// What goes in the column before each tweet in the list to say what kind of
+/// tweet it is.
+struct Glyphs {
+    reply: String,
+    retweet: String,
+    quote: String,
+    /// How wide the column is, which is enough for the widest of them and a
+    /// space, or nothing if they're all empty.
+    width: usize,
+    /// How wide emoji are, as for `Columns`.
+    emoji: Option<usize>,
+}
+
+impl Glyphs {
+    fn new(reply: &str, retweet: &str, quote: &str, emoji: Option<usize>) -> Self {
+        let widest = [reply, retweet, quote]
+            .iter()
+            .map(|glyph| text_width(glyph, emoji))
+            .max()
+            .unwrap_or(0);
+        Glyphs {
+            reply: reply.to_string(),
+            retweet: retweet.to_string(),
+            quote: quote.to_string(),
+            width: if widest == 0 { 0 } else { widest + 1 },
+            emoji,
+        }
+    }
+
+    /// The glyph for `tweet`, padded to the width of the column. Retweets
+    /// are marked as such whatever the original is, and replies that quote
+    /// another tweet as replies.
+    fn column(&self, tweet: &Tweet) -> String {
+        let glyph = if tweet.retweeted_status.is_some() {
+            &self.retweet
+        } else if tweet.in_reply_to_status_id.is_some() {
+            &self.reply
+        } else if tweet.quoted_status_id.is_some() {
+            &self.quote
+        } else {
+            ""
+        };
+        format!(
+            "{}{}",
+            glyph,
+            " ".repeat(self.width - text_width(glyph, self.emoji).min(self.width))
+        )
+    }
+}
+
+/// The styles everything is drawn with, worked out from the `Theme`, along
+/// with the other settings for how tweets are laid out.
+struct Styles {
+    timestamp: Style,
+    text: Style,
+    selection: Style,
+    selection_symbol: String,
+    link: Style,
+    mine: Style,
+    /// For what's only there if it's wanted, like hints, counts and the
+    /// status bar.
+    dim: Style,
+    /// For what needs noticing, like new tweets and marks, and for what's
+    /// gone wrong.
+    warning: Style,
+    error: Style,
+    /// For the unread dot and verified badges.
+    accent: Style,
+    /// For retweet and like counts we've added to ourselves.
+    retweeted: Style,
+    liked: Style,
+    glyphs: Glyphs,
+    palette: Vec<Color>,
+    /// How many levels of quoted tweets are shown before the rest are
+    /// collapsed.
+    quote_depth: usize,
+    /// The time zone timestamps are shown in.
+    zone: Zone,
+    /// How full timestamps are written.
+    time_format: String,
+    /// How wide usernames are padded or cut to, if they're lined up.
+    username_width: Option<usize>,
+    /// How many lines of text unselected tweets are cut to, if any.
+    preview_lines: Option<usize>,
+    /// How many columns each emoji takes up, if that's been set.
+    emoji_width: Option<usize>,
+    /// How many blank rows go between tweets in the list.
+    spacing: u16,
+    /// How to show thumbnails in the detail pane, if they're turned on and
+    /// the terminal can show them.
+    thumbnails: Option<images::Protocol>,
+    /// The keys the timeline's header says to press to jump to the newest
+    /// tweet and to clear marks, if they're bound.
+    top_key: Option<String>,
+    back_key: Option<String>,
+}
+
+impl Styles {
+    /// Fills in anything the config leaves out with the default look. A
+    /// monochrome theme still has its colours checked, but they're swapped
+    /// for modifiers.
+    fn from_config(config: &Config) -> Result<Self> {
+        let theme = &config.theme;
+        let color = |value: &Option<String>| value.as_deref().map(parse_color).transpose();
 
-    #[error("config loading error: {0}")]
-    Config(&'static str),
+        let palette = match &theme.palette {
+            Some(Palette::Named(name)) => named_palette(name)?,
+            Some(Palette::Colors(colors)) if !colors.is_empty() => colors
+                .iter()
+                .map(|c| parse_color(c))
+                .collect::<Result<_>>()?,
+            Some(Palette::Colors(_)) => return Err(Error::Theme("palette is empty".to_string())),
+            None => named_palette("tableau10")?,
+        };
 
-    #[error("config loading error: {0}")]
-    TOMLDeserialize(#[from] toml::de::Error),
+        let mut styles = Styles {
+            timestamp: Style::default().fg(color(&theme.timestamp)?.unwrap_or(Color::DarkGray)),
+            text: match color(&theme.text)? {
+                Some(c) => Style::default().fg(c),
+                None => Style::default(),
+            },
+            selection: match theme.selection.as_deref() {
+                None | Some("reverse") => Style::default().add_modifier(Modifier::REVERSED),
+                Some(c) => Style::default().bg(parse_color(c)?),
+            },
+            selection_symbol: theme.selection_symbol.clone().unwrap_or_default(),
+            link: Style::default()
+                .fg(color(&theme.link)?.unwrap_or(Color::Cyan))
+                .add_modifier(Modifier::UNDERLINED),
+            mine: Style::default().fg(color(&theme.mine)?.unwrap_or(Color::Blue)),
+            dim: Style::default().fg(Color::DarkGray),
+            warning: Style::default().fg(Color::Yellow),
+            error: Style::default().fg(Color::Red),
+            accent: Style::default().fg(Color::Cyan),
+            retweeted: Style::default().fg(Color::Green),
+            liked: Style::default().fg(Color::Red),
+            glyphs: Glyphs::new(
+                theme.reply_glyph.as_deref().unwrap_or("↩"),
+                theme.retweet_glyph.as_deref().unwrap_or("🔁"),
+                theme.quote_glyph.as_deref().unwrap_or("❝"),
+                config.ui.emoji_width()?,
+            ),
+            palette,
+            quote_depth: config.ui.quote_depth(),
+            zone: config.ui.timezone()?,
+            time_format: config.ui.time_format(),
+            // There has to be room for at least a letter and the ellipsis.
+            username_width: config.ui.username_width.map(|width| width.max(2)),
+            preview_lines: config.ui.preview_lines.map(|lines| lines.max(1)),
+            emoji_width: config.ui.emoji_width()?,
+            spacing: match config.ui.density() {
+                Density::Compact => 0,
+                Density::Comfortable => 1,
+            },
+            thumbnails: if config.ui.image_previews() {
+                images::detect()
+            } else {
+                None
+            },
+            top_key: config.keymap.key(Action::Top),
+            back_key: config.keymap.key(Action::Back),
+        };
+        if theme.monochrome() {
+            styles.timestamp = Style::default();
+            styles.text = Style::default();
+            styles.selection = Style::default().add_modifier(Modifier::REVERSED);
+            styles.link = Style::default().add_modifier(Modifier::UNDERLINED);
+            styles.mine = Style::default().add_modifier(Modifier::BOLD);
+            styles.dim = Style::default();
+            // Without colour, these need something else to stand out by.
+            styles.warning = Style::default().add_modifier(Modifier::BOLD);
+            styles.error = Style::default().add_modifier(Modifier::BOLD);
+            styles.accent = Style::default();
+            styles.retweeted = Style::default().add_modifier(Modifier::BOLD);
+            styles.liked = Style::default().add_modifier(Modifier::BOLD);
+            styles.palette = vec![];
+        }
+        Ok(styles)
+    }
 
-    #[error("config saving error: {0}")]
-    TOMLSerialize(#[from] toml::ser::Error),
+    /// The style for the `i`th username in the timeline, which is only bold
+    /// without a palette.
+    fn author(&self, i: usize) -> Style {
+        let style = Style::default().add_modifier(Modifier::BOLD);
+        match self.palette.len() {
+            0 => style,
+            n => style.fg(self.palette[i % n]),
+        }
+    }
+}
 
-    #[error("twitter error: {0}")]
-    Twitter(#[from] egg_mode::error::Error),
+fn named_palette(name: &str) -> Result<Vec<Color>> {
+    let colors: &[colorous::Color] = match name.to_lowercase().as_str() {
+        "tableau10" => &colorous::TABLEAU10,
+        "category10" => &colorous::CATEGORY10,
+        "accent" => &colorous::ACCENT,
+        "dark2" => &colorous::DARK2,
+        "paired" => &colorous::PAIRED,
+        "pastel1" => &colorous::PASTEL1,
+        "pastel2" => &colorous::PASTEL2,
+        "set1" => &colorous::SET1,
+        "set2" => &colorous::SET2,
+        "set3" => &colorous::SET3,
+        _ => return Err(Error::Theme(format!("unknown palette {:?}", name))),
+    };
+    Ok(colors.iter().map(|c| Color::Rgb(c.r, c.g, c.b)).collect())
 }
 
-type Result<T> = std::result::Result<T, Error>;
+/// Parses a hex colour like `#1da1f2`, or the name of a terminal colour.
+fn parse_color(value: &str) -> Result<Color> {
+    if let Some(hex) = value.strip_prefix('#') {
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+        };
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb(r, g, b)),
+            _ => Err(Error::Theme(format!("invalid colour {:?}", value))),
+        };
+    }
+
+    Ok(match value.to_lowercase().as_str() {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" => Color::Gray,
+        "darkgray" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return Err(Error::Theme(format!("invalid colour {:?}", value))),
+    })
+}
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Config {
-    twitter: Twitter,
+/// The `Filters` from the config, ready to match against.
+struct Mutes {
+    keywords: Vec<Pattern>,
+    users: Vec<Pattern>,
+    /// Lowercased. Empty means every language is shown.
+    languages: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Twitter {
-    key: String,
-    secret: String,
-    token: Option<Token>,
+enum Pattern {
+    /// Lowercased, to compare against lowercased text.
+    Text(String),
+    Regex(regex::Regex),
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct Token {
-    consumer: KeyPair,
-    access: KeyPair,
+impl Mutes {
+    fn new(filters: &Filters) -> Result<Self> {
+        let compile = |patterns: &[String]| -> Result<Vec<Pattern>> {
+            patterns.iter().map(|p| Pattern::new(p)).collect()
+        };
+        Ok(Mutes {
+            keywords: compile(&filters.keywords)?,
+            users: compile(&filters.users)?,
+            languages: filters.languages.iter().map(|l| l.to_lowercase()).collect(),
+        })
+    }
+
+    /// Whether `tweet` should be hidden: if it mentions a muted keyword, if a
+    /// muted user wrote or retweeted it, or if it's in a language that isn't
+    /// listed. Tweets in no language Twitter could tell are never hidden for
+    /// it.
+    fn hides(&self, tweet: &Tweet) -> bool {
+        let original = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+        let text = original.text.to_lowercase();
+        let unlisted = language(original).is_some_and(|lang| {
+            !self.languages.is_empty() && !self.languages.contains(&lang.to_lowercase())
+        });
+        unlisted
+            || self.keywords.iter().any(|pattern| pattern.contains(&text))
+            || [tweet, original]
+                .iter()
+                .filter_map(|tweet| handle(tweet))
+                .any(|user| {
+                    let user = user.to_lowercase();
+                    self.users.iter().any(|pattern| pattern.is(&user))
+                })
+    }
 }
 
-impl From<egg_mode::Token> for Token {
-    fn from(t: egg_mode::Token) -> Self {
-        match t {
-            Access { consumer, access } => Token { access, consumer },
-            Bearer(_) => panic!("wrong token type"),
+impl Pattern {
+    fn new(pattern: &str) -> Result<Self> {
+        if pattern.len() > 1 && pattern.starts_with('/') && pattern.ends_with('/') {
+            let regex = regex::RegexBuilder::new(&pattern[1..pattern.len() - 1])
+                .case_insensitive(true)
+                .build()?;
+            Ok(Pattern::Regex(regex))
+        } else {
+            let text = pattern.trim_start_matches('@').to_lowercase();
+            Ok(Pattern::Text(text))
+        }
+    }
+
+    /// Whether `text` contains a match. `text` must be lowercase.
+    fn contains(&self, text: &str) -> bool {
+        match self {
+            Pattern::Text(keyword) => text.contains(keyword.as_str()),
+            Pattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+
+    /// Whether all of `text` matches. `text` must be lowercase.
+    fn is(&self, text: &str) -> bool {
+        match self {
+            Pattern::Text(user) => text == user,
+            Pattern::Regex(regex) => regex.is_match(text),
         }
     }
 }
 
-impl From<Token> for egg_mode::Token {
-    fn from(t: Token) -> Self {
-        Access {
-            consumer: t.consumer,
-            access: t.access,
+/// The tweets a timeline leaves out of its list, by id.
+#[derive(Default)]
+struct Hidden {
+    muted: HashSet<u64>,
+    /// The retweets left out because a newer retweet of the same tweet
+    /// stands in for them, if retweets are collapsed.
+    collapsed: HashSet<u64>,
+}
+
+impl Hidden {
+    fn contains(&self, id: u64) -> bool {
+        self.muted.contains(&id) || self.collapsed.contains(&id)
+    }
+}
+
+/// Which timeline a `TimelineRenderer` is showing.
+#[derive(Debug, Clone, PartialEq)]
+enum TimelineKind {
+    Home,
+    Mentions,
+    User(String),
+    /// The conversation leading up to the tweet with this id.
+    Thread(u64),
+    /// Tweets matching `query`, either the most recent or the most popular.
+    Search {
+        query: String,
+        popular: bool,
+    },
+    Bookmarks,
+}
+
+impl TimelineKind {
+    fn title(&self) -> String {
+        match self {
+            TimelineKind::Home => "home".to_string(),
+            TimelineKind::Mentions => "mentions".to_string(),
+            TimelineKind::User(handle) => format!("@{}'s timeline", handle),
+            TimelineKind::Thread(_) => "thread (esc to go back)".to_string(),
+            TimelineKind::Search { query, popular } => format!(
+                "search for \"{}\", {} first (p to switch)",
+                query,
+                if *popular { "popular" } else { "recent" }
+            ),
+            TimelineKind::Bookmarks => "bookmarks".to_string(),
         }
     }
 }
 
 struct TimelineRenderer {
-    timeline: Timeline,
-    tweets: BTreeMap<DateTime<Utc>, Tweet>,
+    kind: TimelineKind,
+    styles: Rc<Styles>,
+    /// Which tweets to leave out of the list. Muted tweets are still kept, so
+    /// that paging back carries on from the oldest tweet whether or not it's
+    /// shown.
+    mutes: Rc<Mutes>,
+    /// Keyed by id as well as time, because tweets are only timestamped to
+    /// the second and several can easily land in the same one.
+    tweets: BTreeMap<(DateTime<Utc>, u64), Tweet>,
+    state: ListState,
+    requests: mpsc::UnboundedSender<Request>,
+    results: mpsc::UnboundedReceiver<Fetched>,
+    /// Whether the poller is waiting on Twitter right now.
+    busy: watch::Receiver<bool>,
+    /// When a fetch last came back successfully.
+    updated_at: Option<Instant>,
+    /// Whether the last fetch failed, and the poller is going to try again.
+    retrying: bool,
+    /// Why the poller gave up, if it has. That's shown on the timeline rather
+    /// than ending the app, unless the token's been revoked.
+    failed: Option<String>,
+    /// Whether the poller has been asked to stop checking for newer tweets.
+    paused: bool,
+    fetching_older: bool,
+    /// Whether the older tweets on their way were only asked for ahead of
+    /// time, so nobody's waiting on them and they can be called off.
+    prefetching: bool,
+    /// Whether asking for older tweets has come back empty, meaning there
+    /// aren't any more.
+    at_end: bool,
+    rate_limit: Option<RateLimit>,
+    max_tweets: usize,
+    /// How tall the list was when it was last drawn.
+    height: u16,
+    /// The index of the tweet at the top of the list when it was last drawn.
+    offset: usize,
+    /// Where each link on screen was drawn.
+    links: Vec<(Rect, Link)>,
+    /// Each listed tweet's lines as they were last laid out, by id, so they
+    /// only need laying out again when something about them changes.
+    laid_out: HashMap<u64, LaidOut>,
+    /// Where the tweets are saved when the renderer is dropped, if anywhere.
+    cache: Option<PathBuf>,
+    /// Whether the oldest tweet is listed first, as in a thread, rather than
+    /// the newest.
+    oldest_first: bool,
+    /// Whether retweets of the same tweet are listed once, as the newest of
+    /// them.
+    collapse_retweets: bool,
+    /// Which tweets are left out of the list, worked out the first time it's
+    /// needed after the tweets or the mutes change, rather than every time
+    /// the list is gone through.
+    hidden: OnceCell<Rc<Hidden>>,
+    /// A tweet that something in the list replies to, but that couldn't be
+    /// fetched, and what to show in its place.
+    missing_parent: Option<(u64, &'static str)>,
+    /// The id of the user who's signed in, whose tweets are marked.
+    me: Option<u64>,
+    /// How whoever's signed in and the selected tweet's author follow each
+    /// other, if that's been looked up.
+    relation: Option<Relation>,
+    /// Where the detail pane left room for a thumbnail on the last frame, and
+    /// the URL of the thumbnail to show there.
+    thumbnail: Option<(Rect, String)>,
+    /// How many tweets have come in above the selection since the list was
+    /// last scrolled to the top.
+    unseen: usize,
+    /// The ids of the tweets marked for liking, retweeting or opening all at
+    /// once.
+    marked: HashSet<u64>,
+    /// A tweet kept in view above the list, however the list scrolls or
+    /// refreshes.
+    pinned: Option<Tweet>,
+    /// How many new tweets have come in since the timeline was last shown,
+    /// for the tab bar to count while it's in the background.
+    unread: usize,
+    /// The newest tweet that's been selected, which it and everything older
+    /// counts as having been read. Only timelines that keep track of this
+    /// have it, and the rest don't mark anything as unread.
+    read_up_to: Option<u64>,
 }
 
 impl TimelineRenderer {
-    fn new(timeline: Timeline) -> Self {
+    /// Starts polling `source` in the background. The poller stops once the
+    /// returned renderer is dropped.
+    fn new(
+        kind: TimelineKind,
+        source: Box<dyn TimelineSource>,
+        ui: &Ui,
+        styles: Rc<Styles>,
+        mutes: Rc<Mutes>,
+    ) -> Self {
+        let (requests, requests_rx) = mpsc::unbounded_channel();
+        let (results_tx, results) = mpsc::unbounded_channel();
+        let (busy_tx, busy) = watch::channel(false);
+        tokio::spawn(poll_timeline(
+            kind.title(),
+            source,
+            ui.refresh_interval(),
+            ui.timeout(),
+            requests_rx,
+            results_tx,
+            busy_tx,
+        ));
+        TimelineRenderer::with_channels(kind, requests, results, busy, ui, styles, mutes)
+    }
+
+    fn with_channels(
+        kind: TimelineKind,
+        requests: mpsc::UnboundedSender<Request>,
+        results: mpsc::UnboundedReceiver<Fetched>,
+        busy: watch::Receiver<bool>,
+        ui: &Ui,
+        styles: Rc<Styles>,
+        mutes: Rc<Mutes>,
+    ) -> Self {
         TimelineRenderer {
-            timeline,
+            kind,
+            styles,
+            mutes,
             tweets: BTreeMap::new(),
+            state: ListState::default(),
+            requests,
+            results,
+            busy,
+            updated_at: None,
+            retrying: false,
+            failed: None,
+            paused: false,
+            fetching_older: false,
+            prefetching: false,
+            at_end: false,
+            rate_limit: None,
+            max_tweets: ui.max_tweets(),
+            height: 0,
+            offset: 0,
+            links: vec![],
+            laid_out: HashMap::new(),
+            cache: None,
+            oldest_first: ui.order() == Order::Oldest,
+            collapse_retweets: ui.collapse_retweets(),
+            hidden: OnceCell::new(),
+            missing_parent: None,
+            me: None,
+            relation: None,
+            thumbnail: None,
+            unseen: 0,
+            marked: HashSet::new(),
+            pinned: None,
+            unread: 0,
+            read_up_to: None,
         }
     }
 
-    async fn update(mut self) -> Result<TimelineRenderer> {
-        let (new_timeline, response) = self.timeline.newer(None).await?;
-        self.timeline = new_timeline;
-        for tweet in response.response {
-            self.tweets.insert(tweet.created_at.clone(), tweet);
+    /// The home timeline starts with whatever was cached the last time it was
+    /// open, and is cached again when it's closed.
+    fn home(token: &egg_mode::Token, ui: &Ui, styles: Rc<Styles>, mutes: Rc<Mutes>) -> Self {
+        let timeline = egg_mode::tweet::home_timeline(token).with_page_size(ui.page_size() as i32);
+        let source = Box::new(timeline);
+        let mut renderer = TimelineRenderer::new(TimelineKind::Home, source, ui, styles, mutes);
+        renderer.cache = cache::path(token);
+        if let Some(path) = renderer.cache.clone() {
+            // A missing or unreadable cache just means starting empty, and
+            // picking up where we left off is just as optional.
+            if let Ok(tweets) = cache::load(&path, Utc::now()) {
+                renderer.insert(tweets);
+            }
+            if let Some(id) = cache::load_position(&path) {
+                renderer.select_at_or_after(id);
+            }
         }
-        Ok(self)
+        renderer.read_up_to = Some(
+            renderer
+                .cache
+                .as_deref()
+                .and_then(cache::load_read)
+                .unwrap_or(0),
+        );
+        renderer
     }
-}
 
-impl Widget for &TimelineRenderer {
-    fn render(self, area: Rect, buf: &mut Buffer) {
-        let colors = colorous::TABLEAU10;
+    /// Stands in for the home timeline when running with `--mock`, playing
+    /// back `tweets` instead of fetching any. Nothing is cached.
+    fn fixture(tweets: Vec<Tweet>, ui: &Ui, styles: Rc<Styles>, mutes: Rc<Mutes>) -> Self {
+        let source = Box::new(fixture::Playback::new(tweets));
+        let mut renderer = TimelineRenderer::new(TimelineKind::Home, source, ui, styles, mutes);
+        renderer.read_up_to = Some(0);
+        renderer
+    }
 
-        let list_items: Vec<tui::widgets::ListItem> = self
-            .tweets
-            .iter()
-            .rev()
+    fn mentions(token: &egg_mode::Token, ui: &Ui, styles: Rc<Styles>, mutes: Rc<Mutes>) -> Self {
+        let timeline =
+            egg_mode::tweet::mentions_timeline(token).with_page_size(ui.page_size() as i32);
+        let source = Box::new(timeline);
+        TimelineRenderer::new(TimelineKind::Mentions, source, ui, styles, mutes)
+    }
+
+    fn search(
+        query: &str,
+        popular: bool,
+        token: &egg_mode::Token,
+        ui: &Ui,
+        styles: Rc<Styles>,
+        mutes: Rc<Mutes>,
+    ) -> Self {
+        let source = Box::new(SearchSource {
+            query: query.to_string(),
+            result_type: if popular {
+                ResultType::Popular
+            } else {
+                ResultType::Recent
+            },
+            token: token.clone(),
+            page_size: ui.page_size().min(MAX_SEARCH_PAGE_SIZE),
+            newest: None,
+        });
+        let kind = TimelineKind::Search {
+            query: query.to_string(),
+            popular,
+        };
+        TimelineRenderer::new(kind, source, ui, styles, mutes)
+    }
+
+    /// A renderer for `handle`'s tweets, starting from a page that has already
+    /// been fetched.
+    fn user(
+        handle: &str,
+        timeline: Timeline,
+        tweets: Vec<Tweet>,
+        ui: &Ui,
+        styles: Rc<Styles>,
+        mutes: Rc<Mutes>,
+    ) -> Self {
+        let kind = TimelineKind::User(handle.to_string());
+        let source = Box::new(timeline);
+        let mut renderer = TimelineRenderer::new(kind, source, ui, styles, mutes);
+        renderer.insert(tweets);
+        renderer
+    }
+
+    /// A renderer for a thread that has already been fetched, oldest first,
+    /// with the tweet it was opened from selected. Threads aren't polled.
+    fn thread(id: u64, tweets: Vec<Tweet>, ui: &Ui, styles: Rc<Styles>, mutes: Rc<Mutes>) -> Self {
+        let kind = TimelineKind::Thread(id);
+        let mut renderer = TimelineRenderer::offline(kind, ui, styles, mutes);
+        renderer.oldest_first = true;
+        renderer.insert(tweets);
+        let last = renderer.listed().count().saturating_sub(1);
+        renderer.state.select(Some(last));
+        renderer
+    }
+
+    fn bookmarks(tweets: Vec<Tweet>, ui: &Ui, styles: Rc<Styles>, mutes: Rc<Mutes>) -> Self {
+        let mut renderer = TimelineRenderer::offline(TimelineKind::Bookmarks, ui, styles, mutes);
+        renderer.insert(tweets);
+        renderer
+    }
+
+    /// A timeline that only shows the tweets it's given.
+    fn offline(kind: TimelineKind, ui: &Ui, styles: Rc<Styles>, mutes: Rc<Mutes>) -> Self {
+        // With nothing on the other ends of these, there's nothing to fetch
+        // and requests for more go nowhere.
+        let (requests, _) = mpsc::unbounded_channel();
+        let (_, results) = mpsc::unbounded_channel();
+        let (_, busy) = watch::channel(false);
+        TimelineRenderer::with_channels(kind, requests, results, busy, ui, styles, mutes)
+    }
+
+    /// The tweets that aren't muted or collapsed, in the order they're listed
+    /// in.
+    fn listed(&self) -> Box<dyn Iterator<Item = &Tweet> + '_> {
+        let hidden = self.hidden();
+        let shown = move |tweet: &&Tweet| !hidden.contains(tweet.id);
+        if self.oldest_first {
+            Box::new(self.tweets.values().filter(shown))
+        } else {
+            Box::new(self.tweets.values().rev().filter(shown))
+        }
+    }
+
+    /// Which tweets are left out of the list, worked out again only if the
+    /// tweets or the mutes have changed since it was last asked.
+    fn hidden(&self) -> Rc<Hidden> {
+        let hidden = self.hidden.get_or_init(|| {
+            let mut hidden = Hidden::default();
+            let mut seen = HashSet::new();
+            for tweet in self.tweets.values().rev() {
+                if self.mutes.hides(tweet) {
+                    hidden.muted.insert(tweet.id);
+                    continue;
+                }
+                match &tweet.retweeted_status {
+                    Some(original) if self.collapse_retweets && !seen.insert(original.id) => {
+                        hidden.collapsed.insert(tweet.id);
+                    }
+                    _ => {}
+                }
+            }
+            Rc::new(hidden)
+        });
+        hidden.clone()
+    }
+
+    /// Works out which tweets are hidden again the next time it's needed,
+    /// after the tweets or the mutes have changed.
+    fn unhide(&mut self) {
+        self.hidden.take();
+    }
+
+    fn set_mutes(&mut self, mutes: Rc<Mutes>) {
+        self.mutes = mutes;
+        self.unhide();
+    }
+
+    /// Everyone who retweeted each tweet, newest first, by the id of the
+    /// tweet they retweeted, if retweets are collapsed. The first of them is
+    /// the one whose retweet is listed.
+    fn retweeters(&self) -> HashMap<u64, Vec<String>> {
+        let mut retweeters: HashMap<u64, Vec<String>> = HashMap::new();
+        if !self.collapse_retweets {
+            return retweeters;
+        }
+        let hidden = self.hidden();
+        for tweet in self.tweets.values().rev() {
+            match &tweet.retweeted_status {
+                Some(original) if !hidden.muted.contains(&tweet.id) => retweeters
+                    .entry(original.id)
+                    .or_default()
+                    .push(screen_name(tweet)),
+                _ => {}
+            }
+        }
+        retweeters
+    }
+
+    /// Whoever else retweeted what `tweet` retweets, going by `retweeters`.
+    fn also_retweeted_by(tweet: &Tweet, retweeters: &HashMap<u64, Vec<String>>) -> Vec<String> {
+        tweet
+            .retweeted_status
+            .as_ref()
+            .and_then(|original| retweeters.get(&original.id))
+            .map_or(vec![], |handles| handles[1..].to_vec())
+    }
+
+    /// Merges in whatever the poller has fetched since the last call.
+    fn update(&mut self) -> Result<()> {
+        while let Ok(fetched) = self.results.try_recv() {
+            if let Fetch::Older(_) = fetched.fetch {
+                self.fetching_older = false;
+                self.prefetching = false;
+            }
+            if fetched.rate_limit.is_some() {
+                self.rate_limit = fetched.rate_limit;
+            }
+            match fetched.tweets {
+                // The poller waits out the limit by itself.
+                Err(Error::Twitter(egg_mode::error::Error::RateLimit(_))) => {}
+                // And tries again after anything else that might go away.
+                Err(Error::Twitter(e)) if !is_fatal(&e) => self.retrying = true,
+                // Only a revoked token is worth going back to authorize for.
+                Err(Error::Twitter(e)) if !is_revoked(&e) => {
+                    self.retrying = false;
+                    self.failed = Some(e.to_string());
+                }
+                tweets => {
+                    let tweets = tweets?;
+                    self.retrying = false;
+                    match fetched.fetch {
+                        Fetch::Older(_) => self.at_end = tweets.is_empty(),
+                        // Whatever's there at first isn't new, it's just
+                        // what the timeline starts with.
+                        Fetch::Newer if self.updated_at.is_some() => {
+                            self.unread += tweets
+                                .iter()
+                                .filter(|tweet| {
+                                    !self.tweets.contains_key(&(tweet.created_at, tweet.id))
+                                        && !self.mutes.hides(tweet)
+                                })
+                                .count();
+                        }
+                        Fetch::Newer => {}
+                    }
+                    self.updated_at = Some(Instant::now());
+                    self.insert(tweets);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds `tweets` to the list. Unless the newest tweet is selected, the
+    /// selection stays on the same tweet, so that new tweets don't move
+    /// what's being read, and they're counted as unseen instead. When it is,
+    /// whichever is newest afterwards is selected.
+    fn insert(&mut self, tweets: Vec<Tweet>) {
+        let listed = self.listed().count();
+        let newest = if self.oldest_first {
+            listed.checked_sub(1)
+        } else {
+            Some(0)
+        };
+        let selected = self.state.selected();
+        let anchor = match selected {
+            Some(i) if Some(i) != newest => self.selected().map(|tweet| (i, tweet.id)),
+            _ => None,
+        };
+
+        for tweet in tweets {
+            self.tweets.insert((tweet.created_at, tweet.id), tweet);
+        }
+        self.unhide();
+
+        if let Some((before, id)) = anchor {
+            let after = self.listed().position(|tweet| tweet.id == id);
+            if let Some(after) = after {
+                // Listed oldest first, the tweets that land above the
+                // selection are older ones, and the new ones go below it.
+                let above = after.saturating_sub(before);
+                let added = self.listed().count().saturating_sub(listed);
+                self.state.select(Some(after));
+                self.offset += above;
+                self.unseen += if self.oldest_first {
+                    added.saturating_sub(above)
+                } else {
+                    above
+                };
+            }
+        } else if self.oldest_first && selected.is_some() {
+            self.state.select(self.listed().count().checked_sub(1));
+        }
+        self.evict();
+    }
+
+    /// Selects the tweet with the given `id`, or if it's gone, the oldest of
+    /// those newer than it. Ids go up over time, so comparing them compares
+    /// when the tweets were posted.
+    fn select_at_or_after(&mut self, id: u64) {
+        let at = self
+            .listed()
             .enumerate()
-            .map(|(i, (_, tweet))| {
-                let c = colors[i % colors.len()];
+            .filter(|(_, t)| t.id >= id)
+            .min_by_key(|(_, t)| t.id);
+        if let Some((i, _)) = at {
+            self.state.select(Some(i));
+        }
+    }
 
-                let sep = Span::from(" ");
-                let timestamp = Span::styled(
-                    tweet.created_at.format("%H:%M:%S").to_string(),
-                    Style::default().fg(Color::DarkGray),
-                );
-                let username = Span::styled(
-                    tweet.user.clone().unwrap().screen_name,
-                    Style::default()
-                        .fg(Color::Rgb(c.r, c.g, c.b))
-                        .add_modifier(Modifier::BOLD),
-                );
-                let text = Span::styled(tweet.text.clone(), Style::default());
+    /// Lays the list out again for a terminal that's changed size. The
+    /// selection and scroll position are kept by index, so the selection is
+    /// found again by id to be sure it stays on the same tweet.
+    fn resized(&mut self) {
+        let selected = self.selected().map(|tweet| tweet.id);
+        self.laid_out.clear();
+        if let Some(id) = selected {
+            self.select_at_or_after(id);
+        }
+        if let Some(i) = self.state.selected() {
+            self.offset = self.offset.min(i);
+        }
+    }
+
+    /// Selects the newest tweet, and forgets about any unseen ones.
+    fn select_newest(&mut self) {
+        let listed = self.listed().count();
+        if self.oldest_first {
+            self.state.select(listed.checked_sub(1));
+        } else {
+            if listed > 0 {
+                self.state.select(Some(0));
+            }
+            self.offset = 0;
+        }
+        self.unseen = 0;
+    }
+
+    /// Selects the oldest tweet loaded, which starts older ones loading, as
+    /// moving to it would.
+    fn select_oldest(&mut self) {
+        let listed = self.listed().count();
+        if listed == 0 {
+            return;
+        }
+        self.state
+            .select(Some(if self.oldest_first { 0 } else { listed - 1 }));
+        self.fetch_older(false);
+    }
+
+    /// Asks for the tweets before the oldest loaded, unless they're already
+    /// on their way or there aren't any. Pausing stops requests of any kind,
+    /// so older tweets wait too. A `prefetch` is asked for before anyone
+    /// needs it, which can be cancelled until something does.
+    fn fetch_older(&mut self, prefetch: bool) {
+        if self.fetching_older {
+            self.prefetching &= prefetch;
+            return;
+        }
+        if self.at_end || self.paused {
+            return;
+        }
+        if let Some(&(_, oldest)) = self.tweets.keys().next() {
+            let request = Request::Fetch(Fetch::Older(oldest));
+            self.fetching_older = self.requests.send(request).is_ok();
+            self.prefetching = self.fetching_older && prefetch;
+        }
+    }
+
+    /// Fetches older tweets once the selection is `from_end` tweets short of
+    /// the oldest loaded: straight away on reaching it, and ahead of time on
+    /// getting within `PREFETCH_DISTANCE`, as long as that leaves the rate
+    /// limit some calls for keeping up with new tweets.
+    fn approach_end(&mut self, from_end: usize) {
+        if from_end == 0 {
+            self.fetch_older(false);
+        } else if from_end <= PREFETCH_DISTANCE && self.calls_to_spare() {
+            self.fetch_older(true);
+        }
+    }
+
+    /// Whether more than `PREFETCH_RESERVE` of the rate limit's calls are
+    /// left. Before the first fetch there's no telling, so there aren't.
+    fn calls_to_spare(&self) -> bool {
+        self.rate_limit.as_ref().is_some_and(|rate_limit| {
+            f64::from(rate_limit.remaining) > f64::from(rate_limit.limit) * PREFETCH_RESERVE
+        })
+    }
+
+    /// Calls off fetching older tweets if they were only being fetched ahead
+    /// of time, since this timeline's no longer being looked at.
+    fn cancel_prefetch(&mut self) {
+        if self.prefetching && self.requests.send(Request::Cancel).is_ok() {
+            self.fetching_older = false;
+            self.prefetching = false;
+        }
+    }
+
+    /// Drops the oldest tweets once there are more than `max_tweets`, so long
+    /// sessions don't grow without bound. Nothing from the top of the list
+    /// down to a screen's height past the selection is dropped, since that
+    /// could be on screen.
+    fn evict(&mut self) {
+        if self.oldest_first {
+            return self.evict_above();
+        }
+        // The selection and everything a screen's height below it stays,
+        // which is counted in what's listed rather than in all the tweets,
+        // since muted and collapsed ones aren't drawn.
+        let visible = self.state.selected().unwrap_or(0) + self.height as usize;
+        let last_visible = self
+            .listed()
+            .take(visible + 1)
+            .last()
+            .map(|tweet| (tweet.created_at, tweet.id));
+        let mut evicted = false;
+        while self.tweets.len() > self.max_tweets {
+            let oldest = *self.tweets.keys().next().unwrap();
+            if last_visible.is_some_and(|last_visible| oldest >= last_visible) {
+                break;
+            }
+            self.tweets.remove(&oldest);
+            self.at_end = false;
+            evicted = true;
+        }
+        if evicted {
+            self.unhide();
+        }
+    }
+
+    /// `evict` for lists with the oldest tweets first. Those are at the top,
+    /// so it's the ones scrolled off the top of the screen that can go, and
+    /// the selection and scrolling move up to match.
+    fn evict_above(&mut self) {
+        let key = |tweet: &Tweet| (tweet.created_at, tweet.id);
+        let first_visible = match self.listed().nth(self.offset) {
+            Some(tweet) => key(tweet),
+            None => return,
+        };
+        let selected = self.selected().map(key);
+
+        let mut evicted = false;
+        while self.tweets.len() > self.max_tweets {
+            let oldest = *self.tweets.keys().next().unwrap();
+            if oldest >= first_visible {
+                break;
+            }
+            self.tweets.remove(&oldest);
+            self.at_end = false;
+            evicted = true;
+        }
+        if evicted {
+            self.unhide();
+        }
+
+        if evicted {
+            let offset = self.listed().position(|tweet| key(tweet) == first_visible);
+            self.offset = offset.unwrap_or(0);
+            if let Some(selected) = selected {
+                let selected = self.listed().position(|tweet| key(tweet) == selected);
+                self.state.select(selected);
+            }
+        }
+    }
+
+    /// Moves the selection one tweet further down the list, stopping at the
+    /// last one. Landing on the oldest loaded tweet fetches older ones.
+    fn select_next(&mut self) {
+        let listed = self.listed().count();
+        if listed == 0 {
+            return;
+        }
+
+        let last = listed - 1;
+        let next = match self.state.selected() {
+            Some(i) => (i + 1).min(last),
+            None => 0,
+        };
+        self.state.select(Some(next));
+
+        if self.oldest_first {
+            // Scrolling down to new tweets is as good as seeing them.
+            self.unseen = self.unseen.min(last - next);
+        } else {
+            self.approach_end(last - next);
+        }
+    }
+
+    /// Counts every tweet loaded as read, if this timeline keeps track.
+    fn mark_all_read(&mut self) {
+        if let (Some(read_up_to), Some(&(_, newest))) =
+            (&mut self.read_up_to, self.tweets.keys().next_back())
+        {
+            *read_up_to = newest.max(*read_up_to);
+        }
+    }
+
+    /// Whether `tweet` is newer than anything that's been read.
+    fn is_unread(&self, tweet: &Tweet) -> bool {
+        self.read_up_to.is_some_and(|id| tweet.id > id)
+    }
+
+    /// The currently selected tweet, if there is one.
+    fn selected(&self) -> Option<&Tweet> {
+        let i = self.state.selected()?;
+        self.listed().nth(i)
+    }
+
+    /// Marks the selected tweet, or unmarks it if it's already marked.
+    fn toggle_mark(&mut self) {
+        if let Some(id) = self.selected().map(|tweet| tweet.id) {
+            if !self.marked.remove(&id) {
+                self.marked.insert(id);
+            }
+        }
+    }
+
+    /// The marked tweets that are still listed, in the order they're listed.
+    fn marked_tweets(&self) -> Vec<&Tweet> {
+        self.listed()
+            .filter(|tweet| self.marked.contains(&tweet.id))
+            .collect()
+    }
+
+    /// Applies `f` to the tweet with the given `id`, wherever it appears: on
+    /// its own, or as the original of any retweets of it.
+    fn update_tweet<F: FnMut(&mut Tweet)>(&mut self, id: u64, mut f: F) {
+        self.laid_out.clear();
+        for tweet in self.tweets.values_mut().chain(&mut self.pinned) {
+            if tweet.id == id {
+                f(tweet);
+            }
+            if let Some(original) = &mut tweet.retweeted_status {
+                if original.id == id {
+                    f(original);
+                }
+            }
+        }
+    }
+
+    /// The link drawn at the screen position `x`, `y`, if there is one.
+    fn link_at(&self, x: u16, y: u16) -> Option<&Link> {
+        self.links
+            .iter()
+            .find(|(area, _)| {
+                x >= area.x && x < area.x + area.width && y >= area.y && y < area.y + area.height
+            })
+            .map(|(_, link)| link)
+    }
+
+    /// Whether a fetch is in progress.
+    fn is_busy(&self) -> bool {
+        *self.busy.borrow()
+    }
+
+    /// Stops or restarts checking for new tweets.
+    fn toggle_paused(&mut self) {
+        self.paused = !self.paused;
+        let _ = self.requests.send(Request::Pause(self.paused));
+    }
+
+    /// Drops the tweet with the given `id`, and any retweets of it.
+    fn remove_tweet(&mut self, id: u64) {
+        let gone = |tweet: &Tweet| {
+            tweet.id == id || tweet.retweeted_status.as_ref().map(|t| t.id) == Some(id)
+        };
+        self.tweets.retain(|_, tweet| !gone(tweet));
+        self.unhide();
+        if self.pinned.as_ref().is_some_and(gone) {
+            self.pinned = None;
+        }
+    }
+
+    /// Pins the selected tweet above the list, in place of whatever was
+    /// pinned before, or unpins it if it's the one already pinned. Returns
+    /// whether it's pinned now.
+    fn toggle_pin(&mut self) -> Option<bool> {
+        let tweet = self.selected()?.clone();
+        if self.pinned.as_ref().map(|pinned| pinned.id) == Some(tweet.id) {
+            self.pinned = None;
+            Some(false)
+        } else {
+            self.pinned = Some(tweet);
+            Some(true)
+        }
+    }
 
-                let spans = Spans::from(vec![timestamp, sep.clone(), username, sep, text]);
-                tui::widgets::ListItem::new(spans)
+    /// Moves the selection to the next tweet down the list by the same author
+    /// as the selected one, or up the list unless `down` is set, going round
+    /// to the other end if `wrap` is. Returns whether there was one.
+    fn select_by_author(&mut self, down: bool, wrap: bool) -> bool {
+        let current = match self.state.selected() {
+            Some(i) => i,
+            None => return false,
+        };
+        let authors: Vec<Option<u64>> = self
+            .listed()
+            .map(|tweet| {
+                let tweet = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+                tweet.user.as_ref().map(|user| user.id)
             })
             .collect();
-        let list = tui::widgets::List::new(list_items);
+        let author = match authors.get(current) {
+            Some(Some(author)) => *author,
+            _ => return false,
+        };
+
+        let count = authors.len();
+        let steps = match (wrap, down) {
+            (true, _) => count - 1,
+            (false, true) => count - 1 - current,
+            (false, false) => current,
+        };
+        let found = (1..=steps)
+            .map(|step| {
+                if down {
+                    (current + step) % count
+                } else {
+                    (current + count - step) % count
+                }
+            })
+            .find(|&i| authors[i] == Some(author));
+        let i = match found {
+            Some(i) => i,
+            None => return false,
+        };
+        self.state.select(Some(i));
+        // Whatever's between the selection and the newest tweet is still to
+        // be seen.
+        self.unseen = if self.oldest_first {
+            self.unseen.min(count - 1 - i)
+        } else {
+            self.unseen.min(i)
+        };
+        true
+    }
+
+    /// Moves the selection one tweet further up the list, stopping at the top.
+    fn select_previous(&mut self) {
+        if self.listed().next().is_none() {
+            return;
+        }
+
+        let previous = match self.state.selected() {
+            Some(i) => i.saturating_sub(1),
+            None => 0,
+        };
+        self.state.select(Some(previous));
 
-        list.render(area, buf);
+        if !self.oldest_first {
+            // Scrolling up past new tweets is as good as seeing them.
+            self.unseen = self.unseen.min(previous);
+        } else {
+            self.approach_end(previous);
+        }
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let stdout = io::stdout().into_raw_mode()?;
-    let stdout = MouseTerminal::from(stdout);
-    let stdout = AlternateScreen::from(stdout);
-    let backend = TermionBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+impl Drop for TimelineRenderer {
+    fn drop(&mut self) {
+        if let Some(path) = &self.cache {
+            // Nothing is lost if this fails except a head start next time.
+            let _ = cache::save(path, self.tweets.values());
+            if let Some(tweet) = self.selected() {
+                let _ = cache::save_position(path, tweet.id);
+            }
+            if let Some(id) = self.read_up_to {
+                let _ = cache::save_read(path, id);
+            }
+        }
+    }
+}
 
-    let token = get_token().await?;
-    let timeline = egg_mode::tweet::home_timeline(&token).with_page_size(30);
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Fetch {
+    Newer,
+    /// Tweets older than the given id. The renderer decides where this starts
+    /// from, rather than the poller, since it may have dropped old tweets.
+    Older(u64),
+}
+
+/// How the current account and someone else follow each other.
+#[derive(Debug, Clone, Copy)]
+struct Relation {
+    following: bool,
+    followed_by: bool,
+}
+
+impl Relation {
+    fn describe(&self) -> &'static str {
+        match (self.following, self.followed_by) {
+            (true, true) => "you follow each other",
+            (true, false) => "following",
+            (false, true) => "follows you",
+            (false, false) => "not following",
+        }
+    }
+}
+
+/// What a poller can be asked to do.
+enum Request {
+    Fetch(Fetch),
+    /// Stops checking for newer tweets until asked to resume, which checks
+    /// straight away.
+    Pause(bool),
+    /// Drops the older tweets being fetched, if they are, without waiting
+    /// for them or sending them on.
+    Cancel,
+}
+
+enum Event {
+    Key(Key),
+    Mouse(MouseEvent),
+    UserTimeline(String, egg_mode::error::Result<(Timeline, Vec<Tweet>)>),
+    /// A tweet composed on the `from` timeline was sent.
+    Sent {
+        from: TimelineKind,
+        result: egg_mode::error::Result<Box<Tweet>>,
+    },
+    /// The tweets of a thread in `sent` were sent, and those in `unsent`
+    /// weren't because sending the first of them failed. It was composed on
+    /// the `from` timeline.
+    ThreadSent {
+        from: TimelineKind,
+        sent: Vec<Tweet>,
+        unsent: Vec<String>,
+        result: egg_mode::error::Result<()>,
+    },
+    Toggled {
+        toggle: Toggle,
+        id: u64,
+        on: bool,
+        result: egg_mode::error::Result<Box<Tweet>>,
+    },
+    /// A toggle was sent for each of the marked tweets, with how each went.
+    BatchToggled {
+        toggle: Toggle,
+        on: bool,
+        results: Vec<(u64, egg_mode::error::Result<Box<Tweet>>)>,
+    },
+    /// The current account's user id and handle were looked up, for the
+    /// `account`th account.
+    Verified {
+        account: usize,
+        result: egg_mode::error::Result<(u64, String)>,
+    },
+    Deleted {
+        id: u64,
+        result: egg_mode::error::Result<()>,
+    },
+    /// How the `account`th account relates to the user `id` was looked up.
+    Related {
+        account: usize,
+        id: u64,
+        result: egg_mode::error::Result<Relation>,
+    },
+    /// The `account`th account followed or unfollowed `handle`.
+    Followed {
+        account: usize,
+        id: u64,
+        handle: String,
+        follow: bool,
+        result: egg_mode::error::Result<Box<TwitterUser>>,
+    },
+    Messages(egg_mode::error::Result<Vec<Conversation>>),
+    /// The profile of the user with this `id` was fetched.
+    Profile {
+        id: u64,
+        result: egg_mode::error::Result<Box<TwitterUser>>,
+    },
+    /// The thumbnail at `url` was fetched, as a PNG.
+    Thumbnail {
+        url: String,
+        result: Result<Vec<u8>>,
+    },
+    Opened(io::Result<()>),
+    /// The tweet with this `id` was fetched as the next parent up the thread
+    /// opened from the tweet with the id `thread`.
+    Parent {
+        thread: u64,
+        id: u64,
+        result: egg_mode::error::Result<Box<Tweet>>,
+    },
+    Error(Error),
+}
+
+/// Reads keys and mouse events from stdin on a dedicated thread, because
+/// termion's reads block. There's only ever one of these: once it's started,
+/// it's always partway through reading stdin, so anything else reading from
+/// it would lose what's typed to this. Everything typed after it starts has
+/// to come from the returned receiver instead, even between runs of the UI.
+fn spawn_input() -> std::sync::mpsc::Receiver<Event> {
+    let (events, input) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for event in io::stdin().events() {
+            let event = match event {
+                Ok(termion::event::Event::Key(key)) => Event::Key(key),
+                Ok(termion::event::Event::Mouse(mouse)) => Event::Mouse(mouse),
+                Ok(termion::event::Event::Unsupported(_)) => continue,
+                Err(e) => Event::Error(e.into()),
+            };
+            if events.send(event).is_err() {
+                return;
+            }
+        }
+    });
+    input
+}
+
+/// Reads a line typed at the normal terminal from the input thread, for when
+/// it's already running and would take the line for itself otherwise. The
+/// terminal isn't in raw mode then, so it echoes and edits the line itself,
+/// and only hands it over once enter is pressed.
+fn read_line(input: &std::sync::mpsc::Receiver<Event>) -> Result<String> {
+    // Anything typed before now was meant for the UI.
+    while input.try_recv().is_ok() {}
+    let mut line = String::new();
+    loop {
+        match input.recv() {
+            Ok(Event::Key(Key::Char('\n'))) => return Ok(line),
+            Ok(Event::Key(Key::Char(c))) => line.push(c),
+            Ok(Event::Error(e)) => return Err(e),
+            Ok(_) => {}
+            Err(_) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+        }
+    }
+}
 
-    let mut widget = TimelineRenderer::new(timeline);
+/// Fetches newer tweets from `timeline` every `refresh_interval`, unless
+/// paused, and older tweets whenever asked to on `requests`, sending the
+/// results down `results`. `name` is what the log calls the timeline.
+/// `busy` is set for as long as each fetch is in progress. Polling slows down
+/// as the rate limit runs low, and stops until the limit resets if it runs
+/// out. Failed fetches, and those that take longer than `timeout`, are
+/// retried, backing off each time they fail again, unless the error is
+/// fatal. Runs until either channel closes or a fatal
+/// error, which is sent before returning.
+async fn poll_timeline(
+    name: String,
+    mut source: Box<dyn TimelineSource>,
+    refresh_interval: Duration,
+    timeout: Duration,
+    mut requests: mpsc::UnboundedReceiver<Request>,
+    results: mpsc::UnboundedSender<Fetched>,
+    busy: watch::Sender<bool>,
+) {
+    let mut next_refresh = tokio::time::Instant::now();
+    let mut limit = 0;
+    let mut failures = 0;
+    let mut paused = false;
+    // A fetch asked for while another was in progress.
+    let mut queued = None;
 
     loop {
-        widget = widget.update().await?;
+        let request = match queued.take() {
+            Some(fetch) => Request::Fetch(fetch),
+            None => tokio::select! {
+                _ = tokio::time::delay_until(next_refresh), if !paused => Request::Fetch(Fetch::Newer),
+                request = requests.recv() => match request {
+                    Some(request) => request,
+                    None => return,
+                },
+            },
+        };
+        let fetch = match request {
+            Request::Fetch(fetch) => fetch,
+            Request::Pause(pause) => {
+                paused = pause;
+                next_refresh = tokio::time::Instant::now();
+                continue;
+            }
+            // Nothing's being fetched, so there's nothing to cancel.
+            Request::Cancel => continue,
+        };
+
+        tracing::debug!(timeline = %name, ?fetch, "fetching");
+        let _ = busy.broadcast(true);
+        let page = match fetch {
+            Fetch::Newer => source.newer(),
+            Fetch::Older(oldest) => source.older(oldest),
+        };
+        let mut page = Box::pin(with_timeout(timeout, page));
+        // Requests keep coming while the page is on its way, and older
+        // tweets can be cancelled in the meantime.
+        let response = loop {
+            tokio::select! {
+                response = &mut page => break Some(response),
+                request = requests.recv() => match request {
+                    Some(Request::Fetch(next)) => queued = Some(next),
+                    Some(Request::Pause(pause)) => {
+                        paused = pause;
+                        next_refresh = tokio::time::Instant::now();
+                    }
+                    Some(Request::Cancel) if fetch != Fetch::Newer => break None,
+                    Some(Request::Cancel) => {}
+                    None => return,
+                },
+            }
+        };
+        let _ = busy.broadcast(false);
+        let response = match response {
+            Some(response) => response,
+            None => {
+                tracing::debug!(timeline = %name, ?fetch, "cancelled");
+                continue;
+            }
+        };
 
-        terminal.draw(|f| {
-            f.render_widget(&widget, f.size());
-        })?;
+        let fetched = match response {
+            Ok(response) => {
+                let rate_limit = &response.rate_limit_status;
+                tracing::info!(
+                    timeline = %name,
+                    ?fetch,
+                    tweets = response.response.len(),
+                    remaining = rate_limit.remaining,
+                    limit = rate_limit.limit,
+                    reset = rate_limit.reset,
+                    "fetched"
+                );
+                limit = response.rate_limit_status.limit;
+                failures = 0;
+                Fetched {
+                    fetch,
+                    tweets: Ok(response.response),
+                    rate_limit: Some(response.rate_limit_status),
+                }
+            }
+            Err(egg_mode::error::Error::RateLimit(reset)) => {
+                tracing::warn!(timeline = %name, ?fetch, reset, "rate limited");
+                Fetched {
+                    fetch,
+                    tweets: Err(egg_mode::error::Error::RateLimit(reset).into()),
+                    rate_limit: Some(RateLimit {
+                        limit,
+                        remaining: 0,
+                        reset,
+                    }),
+                }
+            }
+            Err(e) if is_fatal(&e) => {
+                tracing::error!(timeline = %name, ?fetch, "fetch failed for good: {}", e);
+                let _ = results.send(Fetched {
+                    fetch,
+                    tweets: Err(e.into()),
+                    rate_limit: None,
+                });
+                return;
+            }
+            Err(e) => {
+                tracing::warn!(timeline = %name, ?fetch, failures, "fetch failed: {}", e);
+                next_refresh =
+                    tokio::time::Instant::now() + retry_delay(refresh_interval, failures);
+                failures += 1;
+                Fetched {
+                    fetch,
+                    tweets: Err(e.into()),
+                    rate_limit: None,
+                }
+            }
+        };
+
+        if let Some(rate_limit) = &fetched.rate_limit {
+            next_refresh =
+                tokio::time::Instant::now() + refresh_delay(refresh_interval, rate_limit);
+        }
 
-        std::thread::sleep(Duration::from_millis(5000));
+        if results.send(fetched).is_err() {
+            return;
+        }
     }
 }
 
-async fn get_token() -> Result<egg_mode::Token> {
-    let home = match dirs::home_dir() {
-        Some(d) => d,
-        None => return Err(Error::Config("unable to find home directory")),
-    };
+/// A page of tweets on its way from a `TimelineSource`. Trait methods can't
+/// be async, so this is the boxed future an async fn would return.
+type Page<'a> =
+    Pin<Box<dyn Future<Output = egg_mode::error::Result<Response<Vec<Tweet>>>> + Send + 'a>>;
 
-    let config_path = home.join(".config").join("twrs").join("config.toml");
-    let mut config: Config = toml::from_str(&std::fs::read_to_string(&config_path)?)?;
+/// Where a poller gets its tweets from. Each source keeps track of how far
+/// it's got, so all the poller has to do is ask for more.
+trait TimelineSource: Send {
+    /// Tweets newer than any fetched so far, or the first page of them.
+    fn newer(&mut self) -> Page<'_>;
 
-    let token: egg_mode::Token = match config.twitter.token.clone() {
-        Some(t) => t.into(),
-        None => {
-            let con_token =
-                egg_mode::KeyPair::new(config.twitter.key.clone(), config.twitter.secret.clone());
-            let request_token = egg_mode::auth::request_token(&con_token, "oob").await?;
-            let auth_url = egg_mode::auth::authorize_url(&request_token);
+    /// The page of tweets before `oldest`.
+    fn older(&mut self, oldest: u64) -> Page<'_>;
+}
+
+/// The home, mentions and user timelines.
+impl TimelineSource for Timeline {
+    fn newer(&mut self) -> Page<'_> {
+        Box::pin(async move {
+            let response = self.call(self.max_id, None).await?;
+            track_cursor(self, &response.response);
+            Ok(response)
+        })
+    }
+
+    fn older(&mut self, oldest: u64) -> Page<'_> {
+        Box::pin(async move {
+            let response = self.call(None, Some(oldest - 1)).await?;
+            track_cursor(self, &response.response);
+            Ok(response)
+        })
+    }
+}
+
+/// Search results, which are paged through differently to timelines.
+struct SearchSource {
+    query: String,
+    result_type: ResultType,
+    token: egg_mode::Token,
+    page_size: u32,
+    /// The id of the newest result so far.
+    newest: Option<u64>,
+}
+
+impl SearchSource {
+    fn search(&self) -> SearchBuilder {
+        egg_mode::search::search(self.query.clone())
+            .result_type(self.result_type)
+            .count(self.page_size)
+    }
+
+    async fn call(
+        &mut self,
+        search: SearchBuilder,
+    ) -> egg_mode::error::Result<Response<Vec<Tweet>>> {
+        let response = search.call(&self.token).await?;
+        let response = Response::map(response, |result| result.statuses);
+        let page_newest = response.response.iter().map(|tweet| tweet.id).max();
+        self.newest = self.newest.max(page_newest);
+        Ok(response)
+    }
+}
+
+impl TimelineSource for SearchSource {
+    fn newer(&mut self) -> Page<'_> {
+        let search = match self.newest {
+            Some(id) => self.search().since_tweet(id),
+            None => self.search(),
+        };
+        Box::pin(self.call(search))
+    }
+
+    fn older(&mut self, oldest: u64) -> Page<'_> {
+        let search = self.search().max_tweet(oldest - 1);
+        Box::pin(self.call(search))
+    }
+}
+
+/// How long to wait before the first retry of a call to Twitter. Each retry
+/// after that waits about twice as long as the last.
+const RETRY_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The longest a call waits for its rate limit to reset before giving up,
+/// rather than leaving whatever's waiting on it hanging.
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(30);
+
+/// How patient to be with calls to Twitter.
+#[derive(Debug, Clone, Copy)]
+struct Retries {
+    /// How many more times to try a call that fails in a way that might go
+    /// away by itself.
+    times: u32,
+    /// How long each try gets.
+    timeout: Duration,
+}
 
-            println!("visit {}", auth_url);
-            let pin: String = Input::new().with_prompt("PIN").interact_text()?;
-            let (token, _, _) =
-                egg_mode::auth::access_token(con_token, &request_token, pin).await?;
-            config.twitter.token = Some(token.clone().into());
-            std::fs::write(&config_path, toml::to_string_pretty(&config)?)?;
-            token
+impl Retries {
+    fn new(ui: &Ui) -> Self {
+        Retries {
+            times: ui.retries(),
+            timeout: ui.timeout(),
         }
+    }
+}
+
+/// Makes `call`, and makes it again up to `retries.times` more times while it
+/// fails or times out in a way that might go away by itself. Anything else is
+/// passed straight back. Timelines aren't fetched through this, since their
+/// pollers already try again on a schedule of their own. `what` is what the
+/// log calls it.
+async fn with_retry<T, F, Fut>(
+    retries: Retries,
+    what: &str,
+    mut call: F,
+) -> egg_mode::error::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = egg_mode::error::Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        tracing::debug!(call = what, attempt, "calling");
+        let e = match with_timeout(retries.timeout, call()).await {
+            Ok(response) => {
+                tracing::info!(call = what, attempt, "succeeded");
+                return Ok(response);
+            }
+            Err(e) => e,
+        };
+        match retry_wait(&e, attempt) {
+            Some(wait) if attempt < retries.times => {
+                tracing::warn!(call = what, attempt, ?wait, "failed, retrying: {}", e);
+                tokio::time::delay_for(wait).await;
+            }
+            _ => {
+                tracing::error!(call = what, attempt, "failed: {}", e);
+                return Err(e);
+            }
+        }
+        attempt += 1;
+    }
+}
+
+/// Waits for `call` for up to `timeout`, after which it fails the same way
+/// as if the connection had dropped, so that it's tried again like one.
+async fn with_timeout<T, E, Fut>(timeout: Duration, call: Fut) -> std::result::Result<T, E>
+where
+    E: From<egg_mode::error::Error>,
+    Fut: Future<Output = std::result::Result<T, E>>,
+{
+    match tokio::time::timeout(timeout, call).await {
+        Ok(result) => result,
+        Err(_) => {
+            let message = format!("no answer after {}s", timeout.as_secs());
+            let e = io::Error::new(io::ErrorKind::TimedOut, message);
+            Err(egg_mode::error::Error::IOError(e).into())
+        }
+    }
+}
+
+/// How long to wait before trying again after the `attempt`th failure in a
+/// row, which failed with `e`, or `None` if trying again won't help. The
+/// backoff is jittered so that calls that failed together don't all come
+/// back together.
+fn retry_wait(e: &egg_mode::error::Error, attempt: u32) -> Option<Duration> {
+    let transient = match e {
+        egg_mode::error::Error::RateLimit(reset) => {
+            let wait =
+                Duration::from_secs((i64::from(*reset) - Utc::now().timestamp()).max(0) as u64);
+            return Some(wait).filter(|wait| *wait <= MAX_RATE_LIMIT_WAIT);
+        }
+        egg_mode::error::Error::NetError(_) | egg_mode::error::Error::IOError(_) => true,
+        egg_mode::error::Error::BadStatus(status) => status.is_server_error(),
+        // Over capacity, or an internal error.
+        e => has_error_code(e, &[130, 131]),
     };
+    if !transient {
+        return None;
+    }
+    let backoff = RETRY_INTERVAL * (1 << attempt.min(16));
+    Some(backoff.mul_f64(rand::thread_rng().gen_range(0.5, 1.5)))
+}
+
+/// How long to wait before trying again after `failures` failed fetches in a
+/// row, doubling each time up to `MAX_RETRY_INTERVAL`.
+fn retry_delay(refresh_interval: Duration, failures: u32) -> Duration {
+    refresh_interval
+        .checked_mul(1 << failures.min(16))
+        .map_or(MAX_RETRY_INTERVAL, |delay| delay.min(MAX_RETRY_INTERVAL))
+}
+
+/// How long to wait before the next refresh. This is `refresh_interval`
+/// unless that would use up the rest of the rate limit before it resets, in
+/// which case the remaining calls are spread out over the rest of the window.
+fn refresh_delay(refresh_interval: Duration, rate_limit: &RateLimit) -> Duration {
+    let until_reset =
+        Duration::from_secs((i64::from(rate_limit.reset) - Utc::now().timestamp()).max(0) as u64);
+
+    if rate_limit.remaining <= 0 {
+        return until_reset;
+    }
+
+    refresh_interval.max(until_reset / rate_limit.remaining as u32)
+}
+
+/// Widens the range of ids `timeline` has loaded to cover `tweets`.
+///
+/// `Timeline::newer` and `Timeline::older` each overwrite both ends of the
+/// cursor with whatever the last page contained, so paging in one direction
+/// would lose our place in the other. We track the ends ourselves instead.
+fn track_cursor(timeline: &mut Timeline, tweets: &[Tweet]) {
+    for tweet in tweets {
+        timeline.max_id = timeline.max_id.max(Some(tweet.id));
+        timeline.min_id = Some(timeline.min_id.map_or(tweet.id, |id| id.min(tweet.id)));
+    }
+}
+
+/// Below this width there isn't room for the detail pane beside the list.
+const DETAIL_MIN_WIDTH: u16 = 100;
+
+/// How many lines of an author's bio the detail pane shows.
+const BIO_LINES: usize = 3;
+
+/// The most rows a thumbnail takes up in the detail pane.
+const THUMBNAIL_ROWS: u16 = 12;
 
-    Ok(token)
+/// The fewest rows worth showing a thumbnail in.
+const MIN_THUMBNAIL_ROWS: u16 = 4;
+
+impl Widget for &mut TimelineRenderer {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let chunks = Layout::default()
+            .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
+            .split(area);
+
+        let mut header = vec![Span::styled(
+            self.kind.title(),
+            Style::default().add_modifier(Modifier::BOLD),
+        )];
+        if self.unseen > 0 {
+            let jump = match &self.styles.top_key {
+                Some(key) => format!(", press {} to jump", key),
+                None => String::new(),
+            };
+            header.push(Span::styled(
+                format!(
+                    "  {} new tweet{}{}",
+                    self.unseen,
+                    if self.unseen == 1 { "" } else { "s" },
+                    jump
+                ),
+                self.styles.warning,
+            ));
+        }
+        if let Some(failed) = &self.failed {
+            header.push(Span::styled(
+                format!("  couldn't fetch tweets: {}", failed),
+                self.styles.error,
+            ));
+        }
+        let marked = self.marked_tweets().len();
+        if marked > 0 {
+            let clear = match &self.styles.back_key {
+                Some(key) => format!(", {} to clear", key),
+                None => String::new(),
+            };
+            header.push(Span::styled(
+                format!(
+                    "  {} tweet{} marked{}",
+                    marked,
+                    if marked == 1 { "" } else { "s" },
+                    clear
+                ),
+                self.styles.warning,
+            ));
+        }
+        Paragraph::new(Spans::from(header)).render(chunks[0], buf);
+
+        self.links.clear();
+        self.thumbnail = None;
+        if chunks[1].width < DETAIL_MIN_WIDTH {
+            self.render_list(chunks[1], buf, true);
+            return;
+        }
+
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+            .split(chunks[1]);
+        self.render_list(panes[0], buf, false);
+        self.render_detail(panes[1], buf);
+    }
+}
+
+impl TimelineRenderer {
+    /// Draws the list of tweets. Without a detail pane beside it, `show_source`
+    /// is set so that the selected tweet says what it was posted with.
+    fn render_list(&mut self, area: Rect, buf: &mut Buffer, show_source: bool) {
+        let area = self.render_pinned(area, buf);
+        self.height = area.height;
+
+        // The first column is a gutter for marking our own tweets, the ones
+        // marked to act on together, and the ones that haven't been read.
+        let gutter = Rect {
+            width: area.width.min(1),
+            ..area
+        };
+        let area = Rect {
+            x: area.x + 1,
+            width: area.width.saturating_sub(1),
+            ..area
+        };
+        // tui puts the selection symbol before the selected tweet, and
+        // indents the rest to match, leaving this much room for the text.
+        let symbol_width = (self.styles.selection_symbol.width() as u16).min(area.width);
+        let text = Rect {
+            x: area.x + symbol_width,
+            width: area.width - symbol_width,
+            ..area
+        };
+
+        let now = Utc::now();
+        let selected = self.state.selected();
+        let mine: Vec<bool> = self
+            .listed()
+            .map(|tweet| self.me.is_some() && tweet.user.as_ref().map(|user| user.id) == self.me)
+            .collect();
+        let marked: Vec<bool> = self
+            .listed()
+            .map(|tweet| self.marked.contains(&tweet.id))
+            .collect();
+        // Whatever's selected has been read, and so has everything before it.
+        let read = selected
+            .and_then(|i| self.listed().nth(i))
+            .map(|tweet| tweet.id);
+        if let (Some(read_up_to), Some(read)) = (&mut self.read_up_to, read) {
+            *read_up_to = read.max(*read_up_to);
+        }
+        let unread: Vec<bool> = self.listed().map(|tweet| self.is_unread(tweet)).collect();
+
+        // Only the tweets still listed are kept in `laid_out` for next time.
+        let mut previous = std::mem::take(&mut self.laid_out);
+        let mut current = HashMap::new();
+        let mut ids = vec![];
+        let count = self.listed().count();
+        let retweeters = self.retweeters();
+        for (i, tweet) in self.listed().enumerate() {
+            let selected = selected == Some(i);
+            let timestamp = match self.styles.username_width {
+                // Lining tweets up means keeping every timestamp short, and
+                // the same width.
+                Some(_) => format!(
+                    "{:>width$}",
+                    format_timestamp(tweet.created_at, now, false, &self.styles),
+                    width = TIMESTAMP_WIDTH
+                ),
+                None => format_timestamp(tweet.created_at, now, selected, &self.styles),
+            };
+            let layout = ItemLayout {
+                i,
+                timestamp,
+                selected,
+                show_source,
+                width: text.width,
+                missing_parent: self
+                    .missing_parent
+                    .filter(|(id, _)| tweet.in_reply_to_status_id == Some(*id))
+                    .map(|(_, placeholder)| placeholder),
+                end: self.at_end && i == if self.oldest_first { 0 } else { count - 1 },
+                oldest_first: self.oldest_first,
+                also_retweeted_by: TimelineRenderer::also_retweeted_by(tweet, &retweeters),
+            };
+            let laid_out = match previous.remove(&tweet.id) {
+                Some(laid_out) if laid_out.layout == layout => laid_out,
+                _ => LaidOut {
+                    lines: layout.lines(tweet, &self.styles),
+                    layout,
+                },
+            };
+            ids.push(tweet.id);
+            current.insert(tweet.id, laid_out);
+        }
+        let items: Vec<&Lines> = ids.iter().map(|id| &current[id].lines).collect();
+
+        // The list is scrolled here rather than by tui, which doesn't say
+        // where it scrolled to, so that we know where the links ended up.
+        if items.is_empty() {
+            // Put the message in the middle, or as near as it can be while
+            // still fitting if it wraps.
+            let message = self.empty_message();
+            let height = (message.width() as u16 / area.width.max(1) + 1).min(area.height);
+            let middle = Rect {
+                y: area.y + (area.height - height) / 2,
+                height,
+                ..area
+            };
+            Paragraph::new(Span::styled(message, self.styles.dim))
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true })
+                .render(middle, buf);
+            return;
+        }
+        let heights: Vec<usize> = items.iter().map(|item| item.spans.len()).collect();
+        let selected = selected.unwrap_or(0).min(items.len() - 1);
+        self.offset = scroll_offset(self.offset, selected, &heights, self.height as usize);
+
+        let mut y = area.y;
+        let flags = mine[self.offset..]
+            .iter()
+            .zip(&marked[self.offset..])
+            .zip(&unread[self.offset..]);
+        for (item, ((&mine, &marked), &unread)) in items[self.offset..].iter().zip(flags) {
+            if y >= area.bottom() {
+                break;
+            }
+            self.links.extend(link_areas(
+                item,
+                Rect { y, ..text },
+                self.styles.emoji_width,
+            ));
+            let bottom = (y + item.spans.len() as u16).min(area.bottom());
+            // The spacing after a tweet isn't part of it, so isn't marked.
+            let rows = (item.spans.len() as u16).saturating_sub(self.styles.spacing);
+            let end = (y + rows).min(bottom);
+            if (mine || marked) && gutter.width > 0 {
+                let (symbol, style) = if marked {
+                    ("┃", self.styles.warning)
+                } else {
+                    ("▎", self.styles.mine)
+                };
+                for row in y..end {
+                    buf.get_mut(gutter.x, row)
+                        .set_symbol(symbol)
+                        .set_style(style);
+                }
+            } else if unread && gutter.width > 0 && end > y {
+                // Only the first row, so as not to stand out as much.
+                buf.get_mut(gutter.x, y)
+                    .set_symbol("•")
+                    .set_style(self.styles.accent);
+            }
+            y = bottom;
+        }
+
+        // Borrowing the laid out text saves copying all of it every frame.
+        let list_items: Vec<tui::widgets::ListItem> = items
+            .into_iter()
+            .skip(self.offset)
+            .map(|item| tui::widgets::ListItem::new(borrow_lines(item)))
+            .collect();
+        let list = tui::widgets::List::new(list_items)
+            .highlight_style(self.styles.selection)
+            .highlight_symbol(&self.styles.selection_symbol);
+
+        let mut state = ListState::default();
+        state.select(Some(selected - self.offset));
+        StatefulWidget::render(list, area, buf, &mut state);
+
+        // tui highlights the whole of the selected item, spacing and all, so
+        // the spacing is put back to how the rest of the list looks.
+        if self.styles.spacing > 0 {
+            let top = area.y + heights[self.offset..selected].iter().sum::<usize>() as u16;
+            let spacing = Rect {
+                y: top + heights[selected] as u16 - self.styles.spacing,
+                height: self.styles.spacing,
+                ..area
+            };
+            let plain = Style::default()
+                .fg(Color::Reset)
+                .bg(Color::Reset)
+                .remove_modifier(Modifier::all());
+            buf.set_style(spacing.intersection(area), plain);
+        }
+        self.laid_out = current;
+    }
+
+    /// Draws the pinned tweet, if there is one, at the top of `area`, and
+    /// returns what's left below it for the list. It gets no more than half
+    /// of `area`, so the list never disappears behind a long tweet.
+    fn render_pinned(&mut self, area: Rect, buf: &mut Buffer) -> Rect {
+        let tweet = match &self.pinned {
+            Some(tweet) => tweet,
+            None => return area,
+        };
+        // Lined up with the text of the list below.
+        let indent = 1 + self.styles.selection_symbol.width() as u16;
+        let text = Rect {
+            x: area.x + indent.min(area.width),
+            width: area.width.saturating_sub(indent),
+            ..area
+        };
+        let layout = ItemLayout {
+            i: 0,
+            timestamp: format_timestamp(tweet.created_at, Utc::now(), false, &self.styles),
+            selected: false,
+            show_source: false,
+            width: text.width,
+            missing_parent: None,
+            end: false,
+            oldest_first: self.oldest_first,
+            also_retweeted_by: vec![],
+        };
+        let lines = layout.lines(tweet, &self.styles);
+        // There has to be room for the label above and the rule below, too.
+        let rows = (lines.spans.len() as u16)
+            .saturating_sub(self.styles.spacing)
+            .min(area.height / 2)
+            .min(area.height.saturating_sub(2));
+        if rows == 0 {
+            return area;
+        }
+
+        let dim = self.styles.dim;
+        Paragraph::new(Span::styled("📌 pinned", dim)).render(Rect { height: 1, ..text }, buf);
+        let body = Rect {
+            y: area.y + 1,
+            height: rows,
+            ..text
+        };
+        Paragraph::new(borrow_lines(&lines)).render(body, buf);
+        self.links
+            .extend(link_areas(&lines, body, self.styles.emoji_width));
+        let rule = Rect {
+            y: body.bottom(),
+            height: 1,
+            ..area
+        };
+        Paragraph::new(Span::styled("─".repeat(area.width as usize), dim)).render(rule, buf);
+
+        Rect {
+            y: rule.bottom(),
+            height: area.height - rows - 2,
+            ..area
+        }
+    }
+
+    /// What to show in place of the list when there's nothing in it.
+    fn empty_message(&self) -> String {
+        if !self.tweets.is_empty() {
+            return "every tweet here is muted".to_string();
+        }
+        match &self.kind {
+            TimelineKind::Bookmarks => "no bookmarks yet".to_string(),
+            TimelineKind::Thread(_) => "nothing left in this thread".to_string(),
+            _ if self.failed.is_some() => "no tweets — couldn't fetch any".to_string(),
+            _ if self.updated_at.is_none() && self.retrying => {
+                "no tweets yet — couldn't fetch any, trying again…".to_string()
+            }
+            _ if self.updated_at.is_none() => "no tweets yet — fetching…".to_string(),
+            TimelineKind::Search { query, .. } => format!("no results for '{}'", query),
+            // Most likely a new account that isn't following anyone yet.
+            TimelineKind::Home => {
+                "your home timeline is empty — follow some accounts to fill it, or try a search"
+                    .to_string()
+            }
+            _ => "no tweets here yet".to_string(),
+        }
+    }
+
+    /// Shows everything about the selected tweet, in a pane of its own.
+    fn render_detail(&mut self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .borders(Borders::LEFT)
+            .border_style(self.styles.dim);
+        let inner = block.inner(area);
+        block.render(area, buf);
+        // Leave a column between the border and the text.
+        let inner = Rect {
+            x: inner.x + 1,
+            width: inner.width.saturating_sub(1),
+            ..inner
+        };
+
+        let retweeters = self.retweeters();
+        let (lines, thumbnail) = match self.selected() {
+            Some(tweet) => {
+                let original = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+                let others = TimelineRenderer::also_retweeted_by(tweet, &retweeters);
+                (
+                    detail_lines(tweet, &others, self.relation, inner.width, &self.styles),
+                    media(original).first().map(images::thumbnail_url),
+                )
+            }
+            None => return,
+        };
+        let area = Rect {
+            height: inner.height.min(lines.spans.len() as u16),
+            ..inner
+        };
+        self.links
+            .extend(link_areas(&lines, area, self.styles.emoji_width));
+
+        // The thumbnail goes in the space under the text, a line down.
+        let top = lines.spans.len() as u16 + 1;
+        let room = inner.height.saturating_sub(top);
+        if let Some(url) = thumbnail.filter(|_| self.styles.thumbnails.is_some()) {
+            if room >= MIN_THUMBNAIL_ROWS {
+                let area = Rect {
+                    y: inner.y + top,
+                    height: room.min(THUMBNAIL_ROWS),
+                    ..inner
+                };
+                self.thumbnail = Some((area, url));
+            }
+        }
+        Paragraph::new(lines.spans).render(inner, buf);
+    }
+}
+
+/// Where each of `lines`' links lands when they're drawn from the top left of
+/// `area`, leaving out any that fall outside it.
+fn link_areas(lines: &Lines, area: Rect, emoji: Option<usize>) -> Vec<(Rect, Link)> {
+    let mut areas = vec![];
+    for (line, span, link) in &lines.links {
+        let row = area.y + *line as u16;
+        if row >= area.bottom() {
+            continue;
+        }
+        let spans = &lines.spans[*line].0;
+        let x = area.x + (spans_width(&spans[..*span], emoji) as u16).min(area.width);
+        let width = (text_width(&spans[*span].content, emoji) as u16).min(area.right() - x);
+        areas.push((Rect::new(x, row, width, 1), link.clone()));
+    }
+    areas
+}
+
+/// Lays out the selected tweet for the detail pane: who wrote it, with a bit
+/// of their bio and how they're related to us, the whole text, and then when,
+/// how popular and where from. Retweets say who retweeted them, along with
+/// `also_retweeted_by`, whose retweets were collapsed into it.
+fn detail_lines(
+    tweet: &Tweet,
+    also_retweeted_by: &[String],
+    relation: Option<Relation>,
+    width: u16,
+    styles: &Styles,
+) -> Lines {
+    let dim = styles.dim;
+    let mut lines = Lines::default();
+
+    let tweet = match &tweet.retweeted_status {
+        Some(original) => {
+            let mut handles = vec![screen_name(tweet)];
+            handles.extend_from_slice(also_retweeted_by);
+            let credit = format!("🔁 retweeted by {}", list_handles(&handles, true));
+            for words in wrap(&credit, width as usize, styles.emoji_width) {
+                let line: Vec<&str> = words.iter().map(|word| &credit[word.clone()]).collect();
+                lines.push(Spans::from(Span::styled(line.join(" "), dim)));
+            }
+            original
+        }
+        None => tweet,
+    };
+
+    if let Some(user) = &tweet.user {
+        let mut name = vec![
+            Span::styled(
+                user.name.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(format!(" @{}", user.screen_name), dim),
+        ];
+        name.extend(badges(tweet, styles));
+        lines.push(Spans::from(name));
+        if let Some(relation) = relation {
+            lines.push(Spans::from(Span::styled(relation.describe(), dim)));
+        }
+        let bio = LinkedText::bio(user).text;
+        let bio = bio.as_str();
+        let bio_lines = wrap(bio, width as usize, styles.emoji_width);
+        if !bio.trim().is_empty() {
+            for (n, words) in bio_lines.iter().take(BIO_LINES).enumerate() {
+                let mut line: Vec<&str> = words.iter().map(|word| &bio[word.clone()]).collect();
+                if n + 1 == BIO_LINES && bio_lines.len() > BIO_LINES {
+                    line.push("…");
+                }
+                lines.push(Spans::from(Span::styled(line.join(" "), dim)));
+            }
+        }
+    }
+
+    lines.push(Spans::default());
+    lines.extend(replying_to_line(tweet, vec![], styles));
+    lines.extend(hanging_lines(
+        vec![],
+        &LinkedText::new(tweet),
+        width,
+        styles,
+    ));
+    if let Some(indicator) = media_indicator(media(tweet), styles) {
+        lines.push(Spans::from(indicator));
+    }
+    lines.extend(alt_text_lines(tweet, 0, width, styles));
+    if let Some(warning) = missing_alt_text(media(tweet), styles) {
+        lines.push(Spans::from(warning));
+    }
+
+    let mut quoted = tweet.quoted_status.as_deref();
+    let mut depth = 1;
+    if quoted.is_some() {
+        lines.push(Spans::default());
+    }
+    while let Some(quote) = quoted {
+        let mut quote_lead = vec![Span::styled("│ ".repeat(depth), dim)];
+        if depth > styles.quote_depth || !quote_fits(&quote_lead, width, styles.emoji_width) {
+            quote_lead.push(Span::styled(COLLAPSED_QUOTE, dim));
+            lines.push(Spans::from(quote_lead));
+            break;
+        }
+        quote_lead.push(Span::styled(
+            screen_name(quote),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        quote_lead.push(Span::raw(" "));
+        lines.extend(hanging_lines(
+            quote_lead,
+            &LinkedText::new(quote),
+            width,
+            styles,
+        ));
+        quoted = quote.quoted_status.as_deref();
+        depth += 1;
+    }
+
+    lines.push(Spans::default());
+    lines.push(Spans::from(Span::styled(
+        styles
+            .zone
+            .convert(tweet.created_at)
+            .format(&styles.time_format)
+            .to_string(),
+        styles.timestamp,
+    )));
+    let count = |count: i32, noun: &str, mine: Option<bool>, style: Style| {
+        let style = if mine == Some(true) { style } else { dim };
+        Span::styled(
+            format!("{} {}", humanize_count(count.max(0) as u64), noun),
+            style,
+        )
+    };
+    lines.push(Spans::from(vec![
+        count(
+            tweet.retweet_count,
+            "retweets",
+            tweet.retweeted,
+            styles.retweeted,
+        ),
+        Span::raw("  "),
+        count(tweet.favorite_count, "likes", tweet.favorited, styles.liked),
+    ]));
+    if let Some(source) = &tweet.source {
+        lines.push(Spans::from(Span::styled(
+            format!("via {}", source.name),
+            dim,
+        )));
+    }
+
+    lines
+}
+
+/// Where a list of items `heights` tall should start so that the `selected`
+/// one fits in `height` rows, scrolling as little as possible from `offset`.
+fn scroll_offset(offset: usize, selected: usize, heights: &[usize], height: usize) -> usize {
+    let mut offset = offset.min(selected);
+    while offset < selected && heights[offset..=selected].iter().sum::<usize>() > height {
+        offset += 1;
+    }
+    offset
+}
+
+/// Something in a tweet that can be clicked on.
+#[derive(Debug, Clone)]
+enum Link {
+    Url(String),
+    /// A mentioned user's screen name.
+    Mention(String),
+}
+
+/// Everything that affects how a tweet in the list is laid out, apart from
+/// the tweet itself.
+#[derive(PartialEq)]
+struct ItemLayout {
+    /// Where it's listed, which picks the colour of its author.
+    i: usize,
+    timestamp: String,
+    selected: bool,
+    show_source: bool,
+    width: u16,
+    /// What to show in place of the tweet it replies to, if that couldn't be
+    /// fetched.
+    missing_parent: Option<&'static str>,
+    /// Whether it's the oldest tweet there is.
+    end: bool,
+    /// Whether the list is oldest first, which puts the end of the timeline
+    /// above the oldest tweet rather than below.
+    oldest_first: bool,
+    /// Who else retweeted the same tweet, when their retweets are collapsed
+    /// into this one.
+    also_retweeted_by: Vec<String>,
+}
+
+impl ItemLayout {
+    fn lines(&self, tweet: &Tweet, styles: &Styles) -> Lines {
+        let dim = styles.dim;
+        let mut lines = Lines::default();
+        if self.end && self.oldest_first {
+            lines.push(Spans::from(Span::styled("start of timeline", dim)));
+        }
+        if let Some(placeholder) = self.missing_parent {
+            lines.push(Spans::from(Span::styled(placeholder, dim)));
+        }
+        let header = lines.spans.len();
+        lines.extend(tweet_lines(
+            tweet,
+            self.i,
+            self.timestamp.clone(),
+            self.selected,
+            self.show_source,
+            self.width,
+            styles,
+        ));
+        // Everyone's named once it's selected.
+        if !self.also_retweeted_by.is_empty() {
+            let others = list_handles(&self.also_retweeted_by, self.selected);
+            lines.spans[header]
+                .0
+                .push(Span::styled(format!(" with {}", others), dim));
+        }
+        if self.end && !self.oldest_first {
+            lines.push(Spans::from(Span::styled("end of timeline", dim)));
+        }
+        for _ in 0..styles.spacing {
+            lines.push(Spans::default());
+        }
+        lines
+    }
+}
+
+/// A tweet's lines, and the layout they were laid out with.
+struct LaidOut {
+    layout: ItemLayout,
+    lines: Lines,
+}
+
+/// `lines` as text that borrows from them, rather than a copy.
+fn borrow_lines(lines: &Lines) -> Vec<Spans<'_>> {
+    lines
+        .spans
+        .iter()
+        .map(|line| {
+            let spans: Vec<Span> = line
+                .0
+                .iter()
+                .map(|span| Span::styled(span.content.as_ref(), span.style))
+                .collect();
+            Spans::from(spans)
+        })
+        .collect()
+}
+
+/// Lines laid out for display, along with which of their spans are links.
+#[derive(Default)]
+struct Lines {
+    spans: Vec<Spans<'static>>,
+    /// The index of the line, and of the span within it, of each link.
+    links: Vec<(usize, usize, Link)>,
+}
+
+impl Lines {
+    fn push(&mut self, line: Spans<'static>) {
+        self.spans.push(line);
+    }
+
+    fn extend(&mut self, other: Lines) {
+        let offset = self.spans.len();
+        self.spans.extend(other.spans);
+        self.links.extend(
+            other
+                .links
+                .into_iter()
+                .map(|(line, span, link)| (line + offset, span, link)),
+        );
+    }
+}
+
+/// A tweet's text, or a bio, with its t.co links swapped for the URLs they
+/// stand for, and the byte ranges of those links and of any mentions. The
+/// link to a tweet's media is dropped, since the media gets an indicator of
+/// its own.
+struct LinkedText {
+    text: String,
+    links: Vec<(Range<usize>, Link)>,
+}
+
+/// The byte range of an entity, what to show in its place if not the original
+/// text, and where it links to if anywhere.
+type Entity<'a> = ((usize, usize), Option<&'a str>, Option<Link>);
+
+impl LinkedText {
+    fn new(tweet: &Tweet) -> Self {
+        let entities = &tweet.entities;
+        let urls = entities.urls.iter().map(|url| {
+            let target = url.expanded_url.as_ref().unwrap_or(&url.url);
+            let display = Some(url.display_url.as_str()).filter(|d| !d.is_empty());
+            (url.range, display, Some(Link::Url(target.clone())))
+        });
+        let media = entities
+            .media
+            .iter()
+            .flatten()
+            .map(|media| (media.range, Some(""), None));
+        let mentions = entities.user_mentions.iter().map(|mention| {
+            let target = Link::Mention(mention.screen_name.clone());
+            (mention.range, None, Some(target))
+        });
+        LinkedText::with_entities(&tweet.text, urls.chain(media).chain(mentions).collect())
+    }
+
+    /// A user's bio, with its links swapped the same way as a tweet's.
+    fn bio(user: &TwitterUser) -> Self {
+        let description = user.description.as_deref().unwrap_or("");
+        let urls = user.entities.description.urls.iter().map(|url| {
+            let target = url.expanded_url.as_ref().unwrap_or(&url.url);
+            let display = Some(url.display_url.as_str()).filter(|d| !d.is_empty());
+            (url.range, display, Some(Link::Url(target.clone())))
+        });
+        LinkedText::with_entities(description, urls.collect())
+    }
+
+    /// Replaces each entity's range of `original` with its replacement, if it
+    /// has one, and keeps track of where its link ended up, if it has one.
+    fn with_entities(original: &str, mut entities: Vec<Entity>) -> Self {
+        entities.sort_by_key(|&((start, _), _, _)| start);
+
+        let mut text = String::new();
+        let mut links = vec![];
+        let mut end = 0;
+        for ((start, next), replacement, link) in entities {
+            // Entities that overlap one already used, like the same photo
+            // listed twice, or that don't line up with the text are skipped.
+            let replaced = match original.get(start..next) {
+                Some(replaced) if start >= end => replaced,
+                _ => continue,
+            };
+            text.push_str(&decode_html(&original[end..start]));
+            let link_start = text.len();
+            text.push_str(replacement.unwrap_or(replaced));
+            if let Some(link) = link {
+                links.push((link_start..text.len(), link));
+            }
+            end = next;
+        }
+        text.push_str(&decode_html(&original[end..]));
+
+        LinkedText { text, links }
+    }
+}
+
+/// The text of `tweet` as it would be written out by hand, with t.co links
+/// swapped for the URLs they stand for.
+fn plain_text(tweet: &Tweet) -> String {
+    let urls = tweet
+        .entities
+        .urls
+        .iter()
+        .map(|url| (url.range, url.expanded_url.as_deref(), None))
+        .collect();
+    LinkedText::with_entities(&tweet.text, urls).text
+}
+
+/// Twitter escapes `&`, `<` and `>` in tweet text as if it were going into
+/// HTML. This puts them back. Entities are never escaped, so this is only
+/// done to the text around them, which keeps their ranges lined up.
+fn decode_html(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find('&') {
+        decoded.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let (plain, len) = if rest.starts_with("&amp;") {
+            ("&", 5)
+        } else if rest.starts_with("&lt;") {
+            ("<", 4)
+        } else if rest.starts_with("&gt;") {
+            (">", 4)
+        } else {
+            ("&", 1)
+        };
+        decoded.push_str(plain);
+        rest = &rest[len..];
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// How many of the other people who retweeted a tweet are named in the list
+/// before the rest are counted instead.
+const RETWEETERS_NAMED: usize = 1;
+
+/// `handles` written out like "@a, @b and @c". Unless `all` is set, only the
+/// first `RETWEETERS_NAMED` are named when there are more than one over, and
+/// the rest are counted, like "@a and 3 others".
+fn list_handles(handles: &[String], all: bool) -> String {
+    let named = if all || handles.len() <= RETWEETERS_NAMED + 1 {
+        handles.len()
+    } else {
+        RETWEETERS_NAMED
+    };
+    let mut names: Vec<String> = handles[..named]
+        .iter()
+        .map(|handle| format!("@{}", handle))
+        .collect();
+    let rest = handles.len() - named;
+    if rest > 0 {
+        names.push(format!(
+            "{} other{}",
+            rest,
+            if rest == 1 { "" } else { "s" }
+        ));
+    }
+    match names.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, init)) => format!("{} and {}", init.join(", "), last),
+        None => String::new(),
+    }
+}
+
+/// Builds the lines for the `i`th tweet in the timeline, wrapped to `width`.
+///
+/// Retweets get a line crediting the retweeter, followed by the original tweet
+/// as if it were posted directly.
+fn tweet_lines(
+    tweet: &Tweet,
+    i: usize,
+    timestamp: String,
+    selected: bool,
+    show_source: bool,
+    width: u16,
+    styles: &Styles,
+) -> Lines {
+    let sep = Span::raw(" ");
+    let glyph = Span::styled(styles.glyphs.column(tweet), styles.timestamp);
+    let timestamp = Span::styled(timestamp, styles.timestamp);
+
+    match &tweet.retweeted_status {
+        Some(original) => {
+            let mut header = vec![glyph, timestamp, sep];
+            let indent = Span::raw(" ".repeat(spans_width(&header, styles.emoji_width)));
+            header.extend(username(tweet, i, styles));
+            header.push(Span::styled(" 🔁 retweeted", styles.dim));
+            let mut lines = Lines {
+                spans: vec![Spans::from(header)],
+                links: vec![],
+            };
+            lines.extend(status_lines(
+                original,
+                vec![indent],
+                i + 1,
+                selected,
+                selected && show_source,
+                width,
+                styles,
+            ));
+            lines
+        }
+        None => status_lines(
+            tweet,
+            vec![glyph, timestamp, sep],
+            i,
+            selected,
+            selected && show_source,
+            width,
+            styles,
+        ),
+    }
+}
+
+/// The widest a short timestamp gets, which is "just now".
+const TIMESTAMP_WIDTH: usize = 8;
+
+/// How long ago `created_at` was, relative to `now`, like "3m" or "2h". Past
+/// a week the date is more useful than the age, so that's shown instead. The
+/// selected tweet always gets its full timestamp, in the configured format.
+fn format_timestamp(
+    created_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+    selected: bool,
+    styles: &Styles,
+) -> String {
+    let zone = styles.zone;
+    if selected {
+        return zone
+            .convert(created_at)
+            .format(&styles.time_format)
+            .to_string();
+    }
+
+    let age = now.signed_duration_since(created_at);
+    if age.num_seconds() < 10 {
+        "just now".to_string()
+    } else if age.num_minutes() < 1 {
+        format!("{}s", age.num_seconds())
+    } else if age.num_hours() < 1 {
+        format!("{}m", age.num_minutes())
+    } else if age.num_days() < 1 {
+        format!("{}h", age.num_hours())
+    } else if age.num_days() < 7 {
+        format!("{}d", age.num_days())
+    } else {
+        zone.convert(created_at).format("%b %d").to_string()
+    }
+}
+
+/// The language Twitter thinks `tweet` is in, unless it couldn't tell.
+fn language(tweet: &Tweet) -> Option<&str> {
+    tweet.lang.as_deref().filter(|lang| *lang != "und")
+}
+
+/// Lays out a tweet's author and text after `lead`, followed by the tweet it
+/// quotes, if any, indented beneath it. The selected tweet also gets its
+/// language and the alt text of its photos, and `show_source` adds what it
+/// was posted with to the end.
+fn status_lines(
+    tweet: &Tweet,
+    mut lead: Vec<Span<'static>>,
+    i: usize,
+    selected: bool,
+    show_source: bool,
+    width: u16,
+    styles: &Styles,
+) -> Lines {
+    let indent = spans_width(&lead, styles.emoji_width);
+
+    match styles.username_width {
+        Some(width) => lead.extend(fit_width(
+            username(tweet, i, styles),
+            width,
+            styles.emoji_width,
+        )),
+        None => lead.extend(username(tweet, i, styles)),
+    }
+    lead.push(Span::raw(" "));
+    let text_indent = spans_width(&lead, styles.emoji_width);
+    let mut text = hanging_lines(lead, &LinkedText::new(tweet), width, styles);
+    if let Some(max) = styles.preview_lines.filter(|_| !selected) {
+        truncate_lines(&mut text, max, text_indent, styles);
+    }
+    let mut lines = replying_to_line(tweet, vec![Span::raw(" ".repeat(indent))], styles);
+    lines.extend(text);
+    let mut trailer: Vec<Span> = media_indicator(media(tweet), styles).into_iter().collect();
+    let counts = engagement(tweet, styles);
+    if !trailer.is_empty() && !counts.is_empty() {
+        trailer.push(Span::raw(" "));
+    }
+    trailer.extend(counts);
+    if let Some(lang) = language(tweet).filter(|_| selected) {
+        if !trailer.is_empty() {
+            trailer.push(Span::raw(" "));
+        }
+        trailer.push(Span::styled(format!("[{}]", lang), styles.dim));
+    }
+    if let Some(source) = tweet.source.as_ref().filter(|_| show_source) {
+        if !trailer.is_empty() {
+            trailer.push(Span::raw(" "));
+        }
+        trailer.push(Span::styled(format!("via {}", source.name), styles.dim));
+    }
+    append_trailer(
+        &mut lines.spans,
+        trailer,
+        text_indent,
+        width,
+        styles.emoji_width,
+    );
+
+    if selected {
+        lines.extend(alt_text_lines(tweet, text_indent, width, styles));
+    }
+
+    // Each level of quote is indented a little further than the last.
+    let mut quoted = tweet.quoted_status.as_deref();
+    let mut depth = 1;
+    while let Some(quote) = quoted {
+        let mut quote_lead = vec![
+            Span::raw(" ".repeat(indent)),
+            Span::styled("│ ".repeat(depth), styles.dim),
+        ];
+        if depth > styles.quote_depth || !quote_fits(&quote_lead, width, styles.emoji_width) {
+            quote_lead.push(Span::styled(COLLAPSED_QUOTE, styles.dim));
+            lines.push(Spans::from(quote_lead));
+            break;
+        }
+        quote_lead.extend(username(quote, i + depth, styles));
+        quote_lead.push(Span::raw(" "));
+        let quote_indent = spans_width(&quote_lead, styles.emoji_width);
+        lines.extend(hanging_lines(
+            quote_lead,
+            &LinkedText::new(quote),
+            width,
+            styles,
+        ));
+        let trailer = media_indicator(media(quote), styles).into_iter().collect();
+        append_trailer(
+            &mut lines.spans,
+            trailer,
+            quote_indent,
+            width,
+            styles.emoji_width,
+        );
+        quoted = quote.quoted_status.as_deref();
+        depth += 1;
+    }
+
+    lines
+}
+
+/// The dimmed "replying to @name" line Twitter shows above replies, after
+/// `lead`, with the name linked. Authors carrying on threads of their own
+/// don't get one, and neither does anything that isn't a reply.
+fn replying_to_line(tweet: &Tweet, mut lead: Vec<Span<'static>>, styles: &Styles) -> Lines {
+    let mut lines = Lines::default();
+    let name = match &tweet.in_reply_to_screen_name {
+        Some(name) => name,
+        None => return lines,
+    };
+    let author = tweet.user.as_ref().map(|user| user.id);
+    if author.is_some() && tweet.in_reply_to_user_id == author {
+        return lines;
+    }
+
+    lead.push(Span::styled("replying to ", styles.dim));
+    lines
+        .links
+        .push((0, lead.len(), Link::Mention(name.clone())));
+    lead.push(Span::styled(format!("@{}", name), styles.link));
+    lines.push(Spans::from(lead));
+    lines
+}
+
+/// Cuts `lines` down to the first `max`, if there are more, and says how many
+/// were hidden on a line of its own, indented by `indent`.
+fn truncate_lines(lines: &mut Lines, max: usize, indent: usize, styles: &Styles) {
+    let hidden = lines.spans.len().saturating_sub(max);
+    if hidden == 0 {
+        return;
+    }
+    lines.spans.truncate(max);
+    lines.links.retain(|(line, _, _)| *line < max);
+    lines.push(Spans::from(vec![
+        Span::raw(" ".repeat(indent)),
+        Span::styled(
+            format!(
+                "… {} more line{}, select to show",
+                hidden,
+                if hidden == 1 { "" } else { "s" }
+            ),
+            styles.dim,
+        ),
+    ]));
+}
+
+/// What's shown in place of quotes nested deeper than `Styles::quote_depth`,
+/// or too deep to fit.
+const COLLAPSED_QUOTE: &str = "[quoted tweet]";
+
+/// The narrowest quoted text gets before it's collapsed instead.
+const MIN_QUOTE_WIDTH: usize = 20;
+
+/// Whether there's room for a quote's text after `lead` in `width` columns.
+fn quote_fits(lead: &[Span], width: u16, emoji: Option<usize>) -> bool {
+    spans_width(lead, emoji) + MIN_QUOTE_WIDTH <= width as usize
+}
+
+/// The photos, video or GIF attached to a tweet. Only the extended entities
+/// list every photo when there are several.
+fn media(tweet: &Tweet) -> &[MediaEntity] {
+    match &tweet.extended_entities {
+        Some(entities) => &entities.media,
+        None => tweet.entities.media.as_deref().unwrap_or(&[]),
+    }
+}
+
+/// The alt text of each of a tweet's photos that has some, indented by
+/// `indent`.
+fn alt_text_lines(tweet: &Tweet, indent: usize, width: u16, styles: &Styles) -> Lines {
+    let photos = media(tweet);
+    let mut lines = Lines::default();
+    for (n, photo) in photos.iter().enumerate() {
+        let alt = match &photo.ext_alt_text {
+            Some(alt) if !alt.trim().is_empty() => alt,
+            _ => continue,
+        };
+        let label = if photos.len() == 1 {
+            "alt: ".to_string()
+        } else {
+            format!("alt {}: ", n + 1)
+        };
+        let lead = vec![
+            Span::raw(" ".repeat(indent)),
+            Span::styled(label, styles.dim),
+        ];
+        let alt = LinkedText {
+            text: alt.clone(),
+            links: vec![],
+        };
+        lines.extend(hanging_lines(lead, &alt, width, styles));
+    }
+    lines
+}
+
+/// A warning that some of `media` has no alt text, for anyone who can't see
+/// it, like "⚠ image has no description" or "⚠ 2 of 3 images have no
+/// description". There's nothing to warn about without any media.
+fn missing_alt_text(media: &[MediaEntity], styles: &Styles) -> Option<Span<'static>> {
+    let missing = media
+        .iter()
+        .filter(|media| {
+            media
+                .ext_alt_text
+                .as_deref()
+                .is_none_or(|alt| alt.trim().is_empty())
+        })
+        .count();
+    let noun = match media.first()?.media_type {
+        MediaType::Photo => "image",
+        MediaType::Video => "video",
+        MediaType::Gif => "GIF",
+    };
+    let text = match (missing, media.len()) {
+        (0, _) => return None,
+        (1, 1) => format!("⚠ {} has no description", noun),
+        (missing, total) if missing == total => format!("⚠ {}s have no description", noun),
+        (1, total) => format!("⚠ 1 of {} {}s has no description", total, noun),
+        (missing, total) => format!("⚠ {} of {} {}s have no description", missing, total, noun),
+    };
+    Some(Span::styled(text, styles.warning))
+}
+
+/// A note of what's attached to a tweet, like "📷 2 photos", since the media
+/// itself can't be shown.
+fn media_indicator(media: &[MediaEntity], styles: &Styles) -> Option<Span<'static>> {
+    let text = match media.first()?.media_type {
+        MediaType::Photo if media.len() == 1 => "📷 photo".to_string(),
+        MediaType::Photo => format!("📷 {} photos", media.len()),
+        MediaType::Video => "🎥 video".to_string(),
+        MediaType::Gif => "🎬 GIF".to_string(),
+    };
+    Some(Span::styled(text, styles.dim))
+}
+
+/// Retweet and like counts for a tweet, each highlighted if we've done it
+/// ourselves. Counts of zero we haven't contributed to are left out.
+fn engagement(tweet: &Tweet, styles: &Styles) -> Vec<Span<'static>> {
+    let counts = [
+        (
+            "🔁",
+            tweet.retweet_count,
+            tweet.retweeted == Some(true),
+            styles.retweeted,
+        ),
+        (
+            "♥",
+            tweet.favorite_count,
+            tweet.favorited == Some(true),
+            styles.liked,
+        ),
+    ];
+
+    let mut spans = vec![];
+    for &(symbol, count, mine, style) in counts.iter() {
+        if count == 0 && !mine {
+            continue;
+        }
+        if !spans.is_empty() {
+            spans.push(Span::raw(" "));
+        }
+        let style = if mine { style } else { styles.dim };
+        spans.push(Span::styled(
+            format!("{} {}", symbol, humanize_count(count.max(0) as u64)),
+            style,
+        ));
+    }
+    spans
+}
+
+/// Shortens counts of a thousand or more, like "1.2K" or "35M". There's one
+/// decimal place below a hundred of the unit, and none from there on.
+fn humanize_count(n: u64) -> String {
+    const UNITS: [(u64, &str); 3] = [(1_000, "K"), (1_000_000, "M"), (1_000_000_000, "B")];
+    let mut unit = match UNITS.iter().rposition(|&(size, _)| n >= size) {
+        Some(unit) => unit,
+        None => return n.to_string(),
+    };
+
+    loop {
+        let (size, suffix) = UNITS[unit];
+        let (n, size) = (n as u128, size as u128);
+        // Rounding can carry over into the next unit up, like 999,999 to
+        // "1000K", which is better said as "1M".
+        let tenths = (n * 10 + size / 2) / size;
+        let shown = if tenths >= 1000 {
+            (n + size / 2) / size * 10
+        } else {
+            tenths
+        };
+        if shown >= 10_000 && unit + 1 < UNITS.len() {
+            unit += 1;
+            continue;
+        }
+        return if shown % 10 == 0 {
+            format!("{}{}", shown / 10, suffix)
+        } else {
+            format!("{}.{}{}", shown / 10, shown % 10, suffix)
+        };
+    }
+}
+
+/// Tacks `trailer` onto the end of the last line, or onto a line of its own
+/// indented by `indent` if it won't fit in `width`.
+fn append_trailer(
+    lines: &mut Vec<Spans<'static>>,
+    trailer: Vec<Span<'static>>,
+    indent: usize,
+    width: u16,
+    emoji: Option<usize>,
+) {
+    if trailer.is_empty() {
+        return;
+    }
+
+    let trailer_width = spans_width(&trailer, emoji);
+    match lines.last_mut() {
+        Some(last) if spans_width(&last.0, emoji) + 1 + trailer_width <= width as usize => {
+            last.0.push(Span::raw(" "));
+            last.0.extend(trailer);
+        }
+        _ => {
+            let mut line = vec![Span::raw(" ".repeat(indent))];
+            line.extend(trailer);
+            lines.push(Spans::from(line));
+        }
+    }
+}
+
+/// The author's screen name, in the colour for the `i`th username, followed by
+/// their badges.
+fn username(tweet: &Tweet, i: usize, styles: &Styles) -> Vec<Span<'static>> {
+    let mut spans = vec![Span::styled(screen_name(tweet), styles.author(i))];
+    spans.extend(badges(tweet, styles));
+    spans
+}
+
+/// Pads `spans` with spaces to `width` columns, or cuts them short with an
+/// ellipsis if they're wider. `emoji` is as for `Columns`.
+fn fit_width(spans: Vec<Span<'static>>, width: usize, emoji: Option<usize>) -> Vec<Span<'static>> {
+    let span_width = |span: &Span| text_width(&span.content, emoji);
+    let total: usize = spans.iter().map(span_width).sum();
+    if total <= width {
+        let mut spans = spans;
+        spans.push(Span::raw(" ".repeat(width - total)));
+        return spans;
+    }
+
+    // Leave a column for the ellipsis.
+    let mut room = width - 1;
+    let mut fitted = vec![];
+    for span in spans {
+        let mut content = String::new();
+        let mut columns = Columns::new(emoji);
+        for c in span.content.chars() {
+            let c_width = columns.width(c);
+            if c_width > room {
+                room = 0;
+                break;
+            }
+            content.push(c);
+            room -= c_width;
+        }
+        let full = content.len() == span.content.len();
+        fitted.push(Span::styled(content, span.style));
+        if !full {
+            fitted.push(Span::styled("…", span.style));
+            break;
+        }
+    }
+    // A wide character that didn't fit can leave a column spare.
+    let used: usize = fitted.iter().map(span_width).sum();
+    fitted.push(Span::raw(" ".repeat(width.saturating_sub(used))));
+    fitted
+}
+
+/// A ✓ if the author is verified, and a 🔒 if their tweets are protected.
+fn badges(tweet: &Tweet, styles: &Styles) -> Vec<Span<'static>> {
+    tweet
+        .user
+        .as_deref()
+        .map(|user| user_badges(user, styles))
+        .unwrap_or_default()
+}
+
+/// `badges` for `user`.
+fn user_badges(user: &TwitterUser, styles: &Styles) -> Vec<Span<'static>> {
+    let mut badges = vec![];
+    if user.verified {
+        badges.push(Span::styled(" ✓", styles.accent));
+    }
+    if user.protected {
+        badges.push(Span::raw(" 🔒"));
+    }
+    badges
+}
+
+/// The author's screen name. Some payloads leave the author out, and those
+/// tweets are shown as by "[unknown]" rather than not at all.
+fn screen_name(tweet: &Tweet) -> String {
+    handle(tweet).unwrap_or("[unknown]").to_string()
+}
+
+/// The author's screen name, if Twitter said who they are.
+fn handle(tweet: &Tweet) -> Option<&str> {
+    tweet.user.as_ref().map(|user| user.screen_name.as_str())
+}
+
+/// Puts `lead` at the start of the first line and wraps `text` into the space
+/// left after it. Continuation lines are indented to line up under the first
+/// line's text. Links are picked out in the link style.
+fn hanging_lines(
+    lead: Vec<Span<'static>>,
+    text: &LinkedText,
+    width: u16,
+    styles: &Styles,
+) -> Lines {
+    let indent = spans_width(&lead, styles.emoji_width);
+    let mut lead = Some(lead);
+    let mut lines = Lines::default();
+
+    let width = (width as usize).saturating_sub(indent);
+    for (n, words) in wrap(&text.text, width, styles.emoji_width)
+        .into_iter()
+        .enumerate()
+    {
+        let mut spans = lead
+            .take()
+            .unwrap_or_else(|| vec![Span::raw(" ".repeat(indent))]);
+        let mut plain = String::new();
+
+        for (i, word) in words.into_iter().enumerate() {
+            if i > 0 {
+                plain.push(' ');
+            }
+            let mut start = word.start;
+            for (range, link) in &text.links {
+                let (link_start, link_end) = (range.start.max(start), range.end.min(word.end));
+                if link_start >= link_end {
+                    continue;
+                }
+                plain.push_str(&text.text[start..link_start]);
+                if !plain.is_empty() {
+                    spans.push(Span::styled(std::mem::take(&mut plain), styles.text));
+                }
+                lines.links.push((n, spans.len(), link.clone()));
+                spans.push(Span::styled(
+                    text.text[link_start..link_end].to_string(),
+                    styles.link,
+                ));
+                start = link_end;
+            }
+            plain.push_str(&text.text[start..word.end]);
+        }
+
+        if !plain.is_empty() {
+            spans.push(Span::styled(plain, styles.text));
+        }
+        lines.spans.push(Spans::from(spans));
+    }
+
+    lines
+}
+
+/// Splits `text` into lines no wider than `width` terminal columns, each given
+/// as the byte ranges of the words on it, to be joined with single spaces.
+/// Lines are broken at whitespace where possible, and mid-word only when a
+/// single word is wider than `width`. Newlines in `text` are always
+/// respected. `emoji` is as for `Columns`.
+fn wrap(text: &str, width: usize, emoji: Option<usize>) -> Vec<Vec<Range<usize>>> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+
+    for paragraph in text.split('\n') {
+        let mut line = Vec::new();
+        let mut line_width = 0;
+
+        for word in paragraph.split_whitespace() {
+            let start = word.as_ptr() as usize - text.as_ptr() as usize;
+            let word_width = text_width(word, emoji);
+
+            if line_width > 0 && line_width + 1 + word_width > width {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0;
+            }
+
+            if word_width > width {
+                let mut piece = start;
+                let mut columns = Columns::new(emoji);
+                for (offset, c) in word.char_indices() {
+                    let char_width = columns.width(c);
+                    if line_width > 0 && line_width + char_width > width {
+                        line.push(piece..start + offset);
+                        lines.push(std::mem::take(&mut line));
+                        piece = start + offset;
+                        line_width = 0;
+                    }
+                    line_width += char_width;
+                }
+                line.push(piece..start + word.len());
+                continue;
+            }
+
+            if line_width > 0 {
+                line_width += 1;
+            }
+            line.push(start..start + word.len());
+            line_width += word_width;
+        }
+
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// How many columns `text` takes up. `emoji` is as for `Columns`.
+fn text_width(text: &str, emoji: Option<usize>) -> usize {
+    let mut columns = Columns::new(emoji);
+    text.chars().map(|c| columns.width(c)).sum()
+}
+
+/// How many columns `spans` take up, measured like `text_width`.
+fn spans_width(spans: &[Span], emoji: Option<usize>) -> usize {
+    spans
+        .iter()
+        .map(|span| text_width(&span.content, emoji))
+        .sum()
+}
+
+/// Measures text a character at a time. Unless `emoji` is set, characters
+/// are as wide as unicode-width says. With it, every emoji is `emoji`
+/// columns wide, and anything that only changes how the emoji before it
+/// looks takes up no room of its own: skin tones, whatever a zero width
+/// joiner joins on, like the 💻 in 👩‍💻, and the second letter of a flag.
+/// A symbol like ♥ is only an emoji when a variation selector asks for it,
+/// so that selector makes up the rest of the emoji's width.
+struct Columns {
+    emoji: Option<usize>,
+    previous: Option<char>,
+    /// How wide the last character was.
+    previous_width: usize,
+    /// Whether the last character started a flag.
+    in_flag: bool,
+}
+
+impl Columns {
+    fn new(emoji: Option<usize>) -> Self {
+        Columns {
+            emoji,
+            previous: None,
+            previous_width: 0,
+            in_flag: false,
+        }
+    }
+
+    fn width(&mut self, c: char) -> usize {
+        let emoji = match self.emoji {
+            Some(emoji) => emoji,
+            None => return c.width().unwrap_or(0),
+        };
+        let joined = self.previous == Some(ZERO_WIDTH_JOINER);
+        let flag_letter = ('\u{1F1E6}'..='\u{1F1FF}').contains(&c);
+        let width = if c == '\u{FE0F}' && !joined && self.previous.is_some() {
+            emoji.saturating_sub(self.previous_width)
+        } else if joined
+            || c == ZERO_WIDTH_JOINER
+            || c == '\u{FE0E}'
+            || c == '\u{FE0F}'
+            || ('\u{1F3FB}'..='\u{1F3FF}').contains(&c)
+            || (flag_letter && self.in_flag)
+        {
+            0
+        } else if flag_letter || is_emoji(c) {
+            emoji
+        } else {
+            c.width().unwrap_or(0)
+        };
+        self.in_flag = flag_letter && !self.in_flag;
+        self.previous = Some(c);
+        self.previous_width = width;
+        width
+    }
+}
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+/// Whether `c` is drawn as an emoji by default. Past U+1F300 that's going by
+/// the blocks most of them are in, but the older symbols are mostly drawn as
+/// text, so only the ones with emoji presentation are listed.
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c,
+        '\u{1F300}'..='\u{1F6FF}'
+            | '\u{1F900}'..='\u{1FAFF}'
+            | '\u{231A}'..='\u{231B}'
+            | '\u{23E9}'..='\u{23EC}'
+            | '\u{23F0}'
+            | '\u{23F3}'
+            | '\u{25FD}'..='\u{25FE}'
+            | '\u{2614}'..='\u{2615}'
+            | '\u{2648}'..='\u{2653}'
+            | '\u{267F}'
+            | '\u{2693}'
+            | '\u{26A1}'
+            | '\u{26AA}'..='\u{26AB}'
+            | '\u{26BD}'..='\u{26BE}'
+            | '\u{26C4}'..='\u{26C5}'
+            | '\u{26CE}'
+            | '\u{26D4}'
+            | '\u{26EA}'
+            | '\u{26F2}'..='\u{26F3}'
+            | '\u{26F5}'
+            | '\u{26FA}'
+            | '\u{26FD}'
+            | '\u{2705}'
+            | '\u{270A}'..='\u{270B}'
+            | '\u{2728}'
+            | '\u{274C}'
+            | '\u{274E}'
+            | '\u{2753}'..='\u{2755}'
+            | '\u{2757}'
+            | '\u{2795}'..='\u{2797}'
+            | '\u{27B0}'
+            | '\u{27BF}'
+            | '\u{2B1B}'..='\u{2B1C}'
+            | '\u{2B50}'
+            | '\u{2B55}'
+            | '\u{1F004}'
+            | '\u{1F0CF}'
+            | '\u{1F18E}'
+            | '\u{1F191}'..='\u{1F19A}'
+    )
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    install_panic_hook();
+
+    // Picking an account and authorizing it are done on the normal terminal,
+    // before the UI takes over the screen.
+    let (config_path, mock) = match parse_args(std::env::args_os().skip(1))? {
+        Command::Run { config, mock } => (
+            match config {
+                Some(path) => path,
+                None => config_path()?,
+            },
+            mock.or_else(|| std::env::var_os("TWRS_MOCK").map(PathBuf::from)),
+        ),
+        Command::Help => {
+            print!("{}", USAGE);
+            return Ok(());
+        }
+        Command::Version => {
+            println!("twrs {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+    };
+    let mut config = match load_config(&config_path) {
+        // Mock mode doesn't need any accounts, so it can make do without.
+        Err(Error::Io(e)) if e.kind() == io::ErrorKind::NotFound && mock.is_some() => {
+            Config::default()
+        }
+        Err(Error::Io(e)) if e.kind() == io::ErrorKind::NotFound => set_up(&config_path)?,
+        config => config?,
+    };
+    logging::init(config.log.level.as_deref())?;
+    let proxy = config.network.proxy()?;
+    if let Some(proxy) = &proxy {
+        tracing::info!(%proxy, "sending requests through a proxy");
+    }
+    egg_mode::set_proxy(proxy)?;
+    tracing::info!(
+        version = env!("CARGO_PKG_VERSION"),
+        mock = mock.is_some(),
+        "starting"
+    );
+    if let Some(path) = &mock {
+        config.fixture = Some(fixture::load(path)?);
+    }
+    let mut account = match mock {
+        Some(_) => 0,
+        None => choose_account(&config)?,
+    };
+    // Started along with the UI, and kept for as long as the process runs.
+    let mut input = None;
+
+    loop {
+        let styles = Styles::from_config(&config)?;
+        let mutes = Mutes::new(&config.filters)?;
+        let token = match mock {
+            // Nothing is sent to Twitter, so the token is never looked at.
+            Some(_) => egg_mode::Token::Access {
+                consumer: KeyPair::new("", ""),
+                access: KeyPair::new("", ""),
+            },
+            None => {
+                let mut shown = String::new();
+                authenticate(&mut config, account, &config_path, |url, error| {
+                    if let Some(e) = error {
+                        println!("that PIN didn't work: {}", e);
+                    }
+                    if url != shown {
+                        if error.is_some() {
+                            println!("starting again with a new link");
+                        }
+                        println!("to authorize twrs, visit {}", url);
+                        shown = url.to_string();
+                    }
+                    match &input {
+                        Some(input) => {
+                            print!("PIN: ");
+                            io::stdout().flush()?;
+                            read_line(input)
+                        }
+                        None => Ok(Input::new().with_prompt("PIN").interact_text()?),
+                    }
+                })
+                .await?
+            }
+        };
+
+        let input = input.get_or_insert_with(spawn_input);
+        let (events_tx, events) = mpsc::unbounded_channel();
+        let app = App::new(
+            config,
+            config_path.clone(),
+            styles,
+            mutes,
+            account,
+            token,
+            events_tx,
+        );
+        let result = {
+            // Declared first so it's dropped last, after the screen and mouse
+            // guards have written their escape codes.
+            let raw = Arc::new(io::stdout().into_raw_mode()?);
+            *RAW_MODE.lock().unwrap() = Some(raw.clone());
+            let stdout = MouseTerminal::from(io::stdout());
+            let stdout = AlternateScreen::from(stdout);
+            let backend = TermionBackend::new(stdout);
+            let mut terminal = Terminal::new(backend)?;
+
+            let result = run(&mut terminal, app, events, input).await;
+            RAW_MODE.lock().unwrap().take();
+            result
+        };
+
+        // With the terminal back to normal, authenticate can ask for a new PIN
+        // and we can start over. The config is read again because the UI may
+        // have changed it.
+        match result {
+            Err(Error::Revoked(revoked)) => {
+                account = revoked;
+                config = load_config(&config_path)?;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Draws `app` and handles its `events`, and what's typed on `input`, until
+/// the user quits. Returning, rather than exiting the process, lets the
+/// terminal guards owned by `main` drop and put the terminal back the way we
+/// found it.
+async fn run<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    mut app: App,
+    mut events: mpsc::UnboundedReceiver<Event>,
+    input: &std::sync::mpsc::Receiver<Event>,
+) -> Result<()> {
+    let mut size = terminal.size()?;
+    while !app.quit {
+        while let Ok(event) = input.try_recv() {
+            app.handle_event(event)?;
+        }
+        while let Ok(event) = events.try_recv() {
+            app.handle_event(event)?;
+        }
+
+        let background = app.tabs.iter_mut().flatten();
+        for timeline in std::iter::once(&mut app.timeline).chain(background) {
+            if let Err(e) = timeline.update() {
+                return Err(match e {
+                    Error::Twitter(e) if is_revoked(&e) => Error::Revoked(app.account),
+                    e => e,
+                });
+            }
+        }
+        app.look_up_relation();
+
+        // Drawing catches up with any change to the terminal's size by
+        // itself, but the timelines are told too, so they lay themselves out
+        // again around the same tweet.
+        let resized = terminal.size()?;
+        if resized != size {
+            size = resized;
+            for timeline in app.timelines() {
+                timeline.resized();
+            }
+        }
+        terminal.draw(|f| app.draw(f))?;
+        app.draw_thumbnail(terminal)?;
+
+        tokio::time::delay_for(TICK_INTERVAL).await;
+    }
+
+    Ok(())
+}
+
+/// How long a message stays in the status line.
+const STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A message shown in the status line until `STATUS_TIMEOUT` has passed.
+struct Status {
+    text: String,
+    is_error: bool,
+    shown_at: Instant,
+}
+
+/// What keys currently do.
+enum Mode {
+    Normal,
+    Prompt(Prompt),
+    Compose(Compose),
+    /// The list of key bindings is showing.
+    Help,
+    /// Waiting for a yes or no before doing something that can't be undone.
+    Confirm(Confirm),
+    /// Direct messages are showing, scrolled down this many lines.
+    Messages(Messages),
+    /// The profile of the user with this id is showing, or will be once
+    /// it's been fetched.
+    Profile(u64),
+}
+
+/// Direct messages, laid out for reading.
+struct Messages {
+    lines: Vec<Spans<'static>>,
+    scroll: u16,
+}
+
+impl Messages {
+    /// Lists `conversations`, each under the name of whoever it's with,
+    /// followed by its messages.
+    fn new(conversations: Vec<Conversation>, styles: &Styles) -> Self {
+        let dim = styles.dim;
+        let now = Utc::now();
+        let mut lines = vec![];
+        for (i, conversation) in conversations.iter().enumerate() {
+            if i > 0 {
+                lines.push(Spans::default());
+            }
+            let (name, handle) = match &conversation.user {
+                Some(user) => (user.name.clone(), user.screen_name.clone()),
+                None => ("[unknown]".to_string(), conversation.id.to_string()),
+            };
+            lines.push(Spans::from(vec![
+                Span::styled(name, styles.author(i)),
+                Span::styled(format!(" @{}", handle), dim),
+            ]));
+            for message in &conversation.messages {
+                let from = if message.received { "them" } else { "you" };
+                lines.push(Spans::from(vec![
+                    Span::styled(
+                        format!(
+                            "{:>8} ",
+                            format_timestamp(message.created_at, now, false, styles)
+                        ),
+                        styles.timestamp,
+                    ),
+                    Span::styled(format!("{}: ", from), dim),
+                    Span::styled(decode_html(&message.text), styles.text),
+                ]));
+            }
+        }
+        if lines.is_empty() {
+            lines.push(Spans::from(Span::styled("no direct messages", dim)));
+        }
+
+        Messages { lines, scroll: 0 }
+    }
+}
+
+/// Something done this session, as `undo` sees it.
+enum Done {
+    /// Likes or retweets, or taking them back, for all of `ids` at once.
+    Toggle {
+        toggle: Toggle,
+        ids: Vec<u64>,
+        on: bool,
+    },
+    /// Something Twitter has no way of taking back, described for saying so.
+    Final(&'static str),
+}
+
+/// How many things done are remembered for undoing.
+const HISTORY_LENGTH: usize = 50;
+
+/// Something that may need confirming before it's done, depending on the
+/// `[confirm]` config.
+enum Confirm {
+    /// Delete the tweet with this id.
+    Delete(u64),
+    /// Follow, or unfollow, the user with this id.
+    Follow {
+        id: u64,
+        handle: String,
+        follow: bool,
+    },
+    /// Like or retweet these tweets, or undo it.
+    Toggle {
+        toggle: Toggle,
+        ids: Vec<u64>,
+        on: bool,
+    },
+}
+
+impl Confirm {
+    /// Whether `confirmations` says to ask before doing this.
+    fn wanted(&self, confirmations: &Confirmations) -> bool {
+        match self {
+            Confirm::Delete(_) => confirmations.delete(),
+            Confirm::Follow { follow: true, .. } => confirmations.follow(),
+            Confirm::Follow { follow: false, .. } => confirmations.unfollow(),
+            // There's nothing to ask about when every marked tweet already
+            // has it done.
+            Confirm::Toggle { ids, .. } if ids.is_empty() => false,
+            Confirm::Toggle {
+                toggle: Toggle::Like,
+                ..
+            } => confirmations.like(),
+            Confirm::Toggle {
+                toggle: Toggle::Retweet,
+                ..
+            } => confirmations.retweet(),
+        }
+    }
+
+    fn question(&self) -> String {
+        match self {
+            Confirm::Delete(_) => "delete this tweet?".to_string(),
+            Confirm::Follow { handle, follow, .. } => {
+                let verb = if *follow { "follow" } else { "unfollow" };
+                format!("{} @{}?", verb, handle)
+            }
+            Confirm::Toggle { toggle, ids, on } => match ids.len() {
+                1 => format!("{} this tweet?", toggle.verb(*on)),
+                n => format!("{} {} tweets?", toggle.verb(*on), n),
+            },
+        }
+    }
+}
+
+/// The bindings worth a reminder in the status line when nothing else is
+/// being shown there. Actions sharing a hint have their keys joined, like
+/// "j/k".
+const HINTS: &[(&[Action], &str)] = &[
+    (&[Action::ScrollDown, Action::ScrollUp], "move"),
+    (&[Action::Compose], "new"),
+    (&[Action::Reply], "reply"),
+    (&[Action::Like], "like"),
+    (&[Action::Retweet], "retweet"),
+    (&[Action::Help], "help"),
+    (&[Action::Quit], "quit"),
+];
+
+/// The hints while direct messages are showing.
+const MESSAGES_HINTS: &[(&[Action], &str)] = &[
+    (&[Action::ScrollDown, Action::ScrollUp], "scroll"),
+    (&[Action::Back], "close"),
+];
+
+/// A single line of input typed into the status line.
+struct Prompt {
+    kind: PromptKind,
+    input: String,
+    /// The command names that tab could have finished, when it couldn't
+    /// tell which was meant.
+    candidates: Vec<&'static str>,
+}
+
+impl Prompt {
+    fn new(kind: PromptKind, input: &str) -> Self {
+        Prompt {
+            kind,
+            input: input.to_string(),
+            candidates: vec![],
+        }
+    }
+
+    /// Finishes the command name being typed, as far as the names it could
+    /// be agree. When they don't agree on any more of it, they're listed
+    /// instead.
+    fn complete_command(&mut self) {
+        if self.input.contains(' ') {
+            return;
+        }
+        let names: Vec<&'static str> = ACTIONS
+            .iter()
+            .map(|(_, name, _, _)| *name)
+            .filter(|name| name.starts_with(self.input.as_str()))
+            .collect();
+        match names[..] {
+            [] => {}
+            [name] => self.input = format!("{} ", name),
+            _ => {
+                // The names are all ASCII, so this can go a byte at a time.
+                let mut common = names[0];
+                for name in &names[1..] {
+                    while !name.starts_with(common) {
+                        common = &common[..common.len() - 1];
+                    }
+                }
+                if common.len() > self.input.len() {
+                    self.input = common.to_string();
+                } else {
+                    self.candidates = names;
+                }
+            }
+        }
+    }
+}
+
+/// A tweet being written. It sticks around while it's being sent so that it
+/// can be edited and retried if sending fails.
+struct Compose {
+    text: String,
+    sending: bool,
+    reply_to: Option<ReplyTo>,
+    quote: Option<Quote>,
+    /// Whether `text` is what's left of a thread that failed part way
+    /// through, which was numbered before any of it was sent.
+    numbered: bool,
+}
+
+impl Compose {
+    fn new() -> Self {
+        Compose {
+            text: String::new(),
+            sending: false,
+            reply_to: None,
+            quote: None,
+            numbered: false,
+        }
+    }
+
+    /// A new tweet quoting `tweet`, which starts out empty. Quoting a retweet
+    /// quotes the original tweet.
+    fn quote(tweet: &Tweet) -> Self {
+        let tweet = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+        Compose {
+            quote: Some(Quote {
+                url: tweet_url(tweet),
+                screen_name: screen_name(tweet),
+                // The preview is short, so it's kept to one paragraph.
+                text: plain_text(tweet)
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            }),
+            ..Compose::new()
+        }
+    }
+
+    /// A reply to `tweet`, addressed to its author and everyone it mentions,
+    /// the way Twitter's own clients do it. Replying to a retweet replies to
+    /// the original tweet.
+    fn reply(tweet: &Tweet) -> Self {
+        let tweet = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+        let author = screen_name(tweet);
+
+        let mut handles: Vec<String> = handle(tweet).map(str::to_string).into_iter().collect();
+        for mention in &tweet.entities.user_mentions {
+            if !handles.contains(&mention.screen_name) {
+                handles.push(mention.screen_name.clone());
+            }
+        }
+
+        let text = handles.iter().map(|h| format!("@{} ", h)).collect();
+
+        Compose {
+            text,
+            sending: false,
+            reply_to: Some(ReplyTo {
+                id: tweet.id,
+                screen_name: author,
+            }),
+            quote: None,
+            numbered: false,
+        }
+    }
+
+    /// The tweets the text will be sent as. Text too long for one tweet, or
+    /// with `THREAD_DELIMITER` lines in it, is sent as a thread.
+    fn tweets(&self) -> Vec<String> {
+        split_thread(&self.text, self.numbered)
+    }
+}
+
+/// A line of just this in the compose box starts a new tweet in the thread.
+const THREAD_DELIMITER: &str = "---";
+
+/// Room left at the end of each tweet in a thread of `total` for its number,
+/// like " 2/5", which is as much as the last one needs.
+fn thread_number_length(total: usize) -> usize {
+    format!(" {}/{}", total, total).len()
+}
+
+/// Splits `text` into tweets short enough to send, first at any
+/// `THREAD_DELIMITER` lines and then, where that isn't enough, between
+/// paragraphs, sentences or words, in that order of preference. Each tweet
+/// of a thread is numbered unless `numbered` says it already is.
+fn split_thread(text: &str, numbered: bool) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    for line in text.split('\n') {
+        if line.trim() == THREAD_DELIMITER {
+            parts.push(String::new());
+        } else {
+            let part = parts.last_mut().unwrap();
+            if !part.is_empty() {
+                part.push('\n');
+            }
+            part.push_str(line);
+        }
+    }
+    parts.retain(|part| !part.trim().is_empty());
+    if parts.len() == 1 && weighted_length(&parts[0]) <= MAX_TWEET_LENGTH {
+        return parts;
+    }
+
+    let split = |limit| -> Vec<String> {
+        parts
+            .iter()
+            .flat_map(|part| split_to_fit(part.trim(), limit, 0))
+            .collect()
+    };
+    if numbered {
+        return split(MAX_TWEET_LENGTH);
+    }
+    // Leaving room for the numbers can make for more tweets, and so longer
+    // numbers, so it's split again with more room until they fit.
+    let mut reserved = thread_number_length(1);
+    let tweets = loop {
+        let tweets = split(MAX_TWEET_LENGTH - reserved);
+        let needed = thread_number_length(tweets.len());
+        if needed <= reserved {
+            break tweets;
+        }
+        reserved = needed;
+    };
+    if tweets.len() == 1 {
+        return tweets;
+    }
+    let total = tweets.len();
+    tweets
+        .into_iter()
+        .enumerate()
+        .map(|(i, tweet)| format!("{} {}/{}", tweet, i + 1, total))
+        .collect()
+}
+
+/// Splits `text` into pieces no longer than `limit`, at the boundaries
+/// `level` and those below it prefer: paragraphs, then sentences, then words,
+/// then anywhere at all. Pieces are packed as full as they'll go.
+fn split_to_fit(text: &str, limit: usize, level: usize) -> Vec<String> {
+    if weighted_length(text) <= limit {
+        return vec![text.to_string()];
+    }
+    let (parts, separator): (Vec<&str>, &str) = match level {
+        0 => (text.split("\n\n").collect(), "\n\n"),
+        1 => (sentences(text), " "),
+        2 => (text.split_whitespace().collect(), " "),
+        _ => return split_chars(text, limit),
+    };
+
+    let mut pieces = vec![];
+    let mut current = String::new();
+    for part in parts
+        .iter()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+    {
+        for piece in split_to_fit(part, limit, level + 1) {
+            let joined = format!("{}{}{}", current, separator, piece);
+            if current.is_empty() {
+                current = piece;
+            } else if weighted_length(&joined) <= limit {
+                current = joined;
+            } else {
+                pieces.push(std::mem::replace(&mut current, piece));
+            }
+        }
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
+
+/// Splits `text` after each full stop, question mark or exclamation mark
+/// that's followed by whitespace.
+fn sentences(text: &str) -> Vec<&str> {
+    let mut sentences = vec![];
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        if let ('.' | '?' | '!', Some(&(next, n))) = (c, chars.peek()) {
+            if n.is_whitespace() {
+                sentences.push(&text[start..next]);
+                start = next;
+            }
+        }
+    }
+    sentences.push(&text[start..]);
+    sentences
+}
+
+/// Splits `text` into pieces no longer than `limit`, wherever that falls.
+fn split_chars(text: &str, limit: usize) -> Vec<String> {
+    let mut pieces = vec![];
+    let mut current = String::new();
+    let mut length = 0;
+    for c in text.chars() {
+        if length + char_weight(c) > limit && !current.is_empty() {
+            pieces.push(std::mem::take(&mut current));
+            length = 0;
+        }
+        current.push(c);
+        length += char_weight(c);
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
+
+/// The tweet a `Compose` is replying to. This is captured when the reply is
+/// started, so that the timeline changing underneath doesn't change it.
+struct ReplyTo {
+    id: u64,
+    screen_name: String,
+}
+
+/// The tweet a `Compose` is quoting, captured when the quote is started like
+/// `ReplyTo` is. It's sent as an attachment rather than in the text, so the
+/// URL doesn't count towards the tweet's length.
+struct Quote {
+    url: String,
+    screen_name: String,
+    text: String,
+}
+
+/// Twitter's limit on the weighted length of a tweet. See `weighted_length`.
+const MAX_TWEET_LENGTH: usize = 280;
+
+/// What a `Prompt` is asking for, and so what happens when it's submitted.
+enum PromptKind {
+    User,
+    Search,
+    /// Where to export the timeline to.
+    Export,
+    /// The name of an action, like `like`. `user`, `search` and `export` can
+    /// be given what they'd otherwise prompt for after a space.
+    Command,
+}
+
+impl PromptKind {
+    fn label(&self) -> &'static str {
+        match self {
+            PromptKind::User => "user: @",
+            PromptKind::Search => "search: ",
+            PromptKind::Export => "export to: ",
+            PromptKind::Command => ":",
+        }
+    }
+}
+
+struct App {
+    config: Config,
+    config_path: PathBuf,
+    styles: Rc<Styles>,
+    /// Which of `config.accounts` is in use.
+    account: usize,
+    token: egg_mode::Token,
+    /// The id of the user `token` belongs to, once it's been looked up.
+    user_id: Option<u64>,
+    /// Their handle, looked up along with their id.
+    screen_name: Option<String>,
+    /// Keeps the mentions poller behind notifications going. Dropping this
+    /// stops it.
+    mentions_poller: Option<mpsc::UnboundedSender<Request>>,
+    mutes: Rc<Mutes>,
+    /// The timeline in the open tab.
+    timeline: TimelineRenderer,
+    /// The timeline a thread was opened from, to go back to when it's closed.
+    previous: Option<TimelineRenderer>,
+    /// Every tab's timeline, in order, apart from the open tab's, which is
+    /// taken out into `timeline` while it's open. The rest are kept polling
+    /// in the background.
+    tabs: Vec<Option<TimelineRenderer>>,
+    /// Which of `tabs` is open.
+    tab: usize,
+    /// The tweets fetched while walking up threads, by id, so that they don't
+    /// have to be fetched again.
+    parents: HashMap<u64, Tweet>,
+    /// How the current account relates to the authors of tweets that have
+    /// been selected, by user id, so each is only looked up once. `None`
+    /// while the lookup is in progress, or if it failed.
+    relations: HashMap<u64, Option<Relation>>,
+    /// The profiles that have been shown, by user id, so each is only
+    /// fetched once. `None` while it's being fetched.
+    profiles: HashMap<u64, Option<Box<TwitterUser>>>,
+    /// The tweets bookmarked with `Action::Bookmark`, by id, and where
+    /// they're saved.
+    bookmarks: HashMap<u64, Tweet>,
+    bookmarks_path: Option<PathBuf>,
+    /// Thumbnails by URL, as PNGs. `None` while one's being fetched, or if
+    /// fetching it failed.
+    thumbnails: HashMap<String, Option<Vec<u8>>>,
+    /// Where a thumbnail was last drawn, and which.
+    thumbnail_shown: Option<(Rect, String)>,
+    /// Opened on the first copy, and kept for as long as twrs runs: on X11
+    /// what's copied is only there while something holds on to it.
+    clipboard: Option<arboard::Clipboard>,
+    mode: Mode,
+    /// The keys typed so far of a binding that takes more than one, like
+    /// `gg`.
+    pending: Vec<Key>,
+    /// The count typed before a motion, like the 5 in `5j`, if any.
+    count: Option<usize>,
+    /// What's been done this session, most recent last, for undoing.
+    history: Vec<Done>,
+    status: Option<Status>,
+    /// How many times the screen has been drawn, which drives the spinner.
+    frame: usize,
+    events: mpsc::UnboundedSender<Event>,
+    quit: bool,
+}
+
+impl App {
+    fn new(
+        config: Config,
+        config_path: PathBuf,
+        styles: Styles,
+        mutes: Mutes,
+        account: usize,
+        token: egg_mode::Token,
+        events: mpsc::UnboundedSender<Event>,
+    ) -> Self {
+        let styles = Rc::new(styles);
+        let mutes = Rc::new(mutes);
+        let mut app = App {
+            // This is replaced by the first tab's timeline once the tabs are
+            // opened, which the bookmarks have to be loaded for first.
+            timeline: TimelineRenderer::offline(
+                TimelineKind::Home,
+                &config.ui,
+                styles.clone(),
+                mutes.clone(),
+            ),
+            config,
+            config_path,
+            styles,
+            mutes,
+            account,
+            token,
+            user_id: None,
+            screen_name: None,
+            mentions_poller: None,
+            mode: Mode::Normal,
+            pending: vec![],
+            count: None,
+            history: vec![],
+            status: None,
+            frame: 0,
+            events,
+            previous: None,
+            tabs: vec![],
+            tab: 0,
+            parents: HashMap::new(),
+            relations: HashMap::new(),
+            profiles: HashMap::new(),
+            bookmarks: HashMap::new(),
+            bookmarks_path: bookmarks::path(),
+            thumbnails: HashMap::new(),
+            thumbnail_shown: None,
+            clipboard: None,
+            quit: false,
+        };
+        if let Some(path) = &app.bookmarks_path {
+            match bookmarks::load(path) {
+                Ok(tweets) => app.bookmarks = tweets.into_iter().map(|t| (t.id, t)).collect(),
+                Err(e) => app.show_error(format!("couldn't load bookmarks: {}", e)),
+            }
+        }
+        app.open_tabs();
+        // With --mock there's no account to look up or fetch mentions for.
+        if app.config.fixture.is_none() {
+            app.look_up_user();
+            app.watch_mentions();
+        }
+        app
+    }
+
+    /// The home timeline for `token`, or the fixture standing in for it.
+    fn home_timeline(
+        config: &Config,
+        token: &egg_mode::Token,
+        styles: &Rc<Styles>,
+        mutes: &Rc<Mutes>,
+    ) -> TimelineRenderer {
+        match &config.fixture {
+            Some(tweets) => {
+                TimelineRenderer::fixture(tweets.clone(), &config.ui, styles.clone(), mutes.clone())
+            }
+            None => TimelineRenderer::home(token, &config.ui, styles.clone(), mutes.clone()),
+        }
+    }
+
+    /// Opens a tab for each timeline in the config, in place of any that
+    /// were open, and shows the startup timeline, or else the first. With
+    /// --mock, only the timelines that don't need Twitter get tabs, and with
+    /// a bearer token, only the ones that don't need a user.
+    fn open_tabs(&mut self) {
+        let mock = self.config.fixture.is_some();
+        let read_only = self.read_only();
+        let allowed = |tab: &Tab| {
+            (!mock || matches!(tab, Tab::Home | Tab::Bookmarks))
+                && (!read_only || !matches!(tab, Tab::Home | Tab::Mentions))
+        };
+        let mut tabs = self.config.ui.tabs().unwrap_or_default();
+        tabs.retain(|tab| allowed(tab));
+        let startup = self.config.ui.startup().filter(|tab| allowed(tab));
+        if let Some(startup) = startup.as_ref().filter(|tab| !tabs.contains(tab)) {
+            tabs.insert(0, startup.clone());
+        }
+        if tabs.is_empty() {
+            tabs.push(if read_only { Tab::Bookmarks } else { Tab::Home });
+        }
+
+        let open = startup
+            .and_then(|startup| tabs.iter().position(|tab| *tab == startup))
+            .unwrap_or(0);
+        let mut timelines: Vec<Option<TimelineRenderer>> = tabs
+            .iter()
+            .map(|tab| Some(self.tab_timeline(tab)))
+            .collect();
+        self.timeline = timelines[open].take().unwrap();
+        self.previous = None;
+        self.tabs = timelines;
+        self.tab = open;
+    }
+
+    fn tab_timeline(&self, tab: &Tab) -> TimelineRenderer {
+        let (ui, styles, mutes) = (&self.config.ui, self.styles.clone(), self.mutes.clone());
+        match tab {
+            Tab::Home => App::home_timeline(&self.config, &self.token, &self.styles, &self.mutes),
+            Tab::Mentions => TimelineRenderer::mentions(&self.token, ui, styles, mutes),
+            Tab::Bookmarks => TimelineRenderer::bookmarks(
+                self.bookmarks.values().cloned().collect(),
+                ui,
+                styles,
+                mutes,
+            ),
+            Tab::User(handle) => {
+                let timeline = egg_mode::tweet::user_timeline(
+                    UserID::from(handle.clone()),
+                    true,
+                    true,
+                    &self.token,
+                )
+                .with_page_size(ui.page_size() as i32);
+                TimelineRenderer::user(handle, timeline, vec![], ui, styles, mutes)
+            }
+            Tab::Search(query) => {
+                TimelineRenderer::search(query, false, &self.token, ui, styles, mutes)
+            }
+        }
+    }
+
+    /// The timeline each tab shows, in order, with the open tab's being the
+    /// one its thread was opened from if there's a thread open.
+    fn tab_timelines(&self) -> impl Iterator<Item = &TimelineRenderer> {
+        let open = self.previous.as_ref().unwrap_or(&self.timeline);
+        self.tabs
+            .iter()
+            .map(move |tab| tab.as_ref().unwrap_or(open))
+    }
+
+    /// Opens the `i`th tab, closing any thread open in this one.
+    fn switch_tab(&mut self, i: usize) {
+        if i >= self.tabs.len() {
+            return;
+        }
+        if let Some(previous) = self.previous.take() {
+            self.timeline = previous;
+        }
+        if i == self.tab {
+            return;
+        }
+        let next = self.tabs[i].take().unwrap();
+        self.timeline.cancel_prefetch();
+        self.tabs[self.tab] = Some(std::mem::replace(&mut self.timeline, next));
+        self.tab = i;
+    }
+
+    /// Each tab's title, numbered for typing before tab to go to it, with
+    /// how many tweets it has that haven't been seen yet, and whether
+    /// fetching them has stopped.
+    fn tab_bar(&self) -> Tabs<'static> {
+        let titles = self
+            .tab_timelines()
+            .enumerate()
+            .map(|(i, timeline)| {
+                let mut title = vec![Span::raw(format!("{} {}", i + 1, timeline.kind.title()))];
+                if timeline.unread > 0 {
+                    title.push(Span::styled(
+                        format!(" ({})", timeline.unread),
+                        self.styles.warning,
+                    ));
+                }
+                if timeline.failed.is_some() {
+                    title.push(Span::styled(" !", self.styles.error));
+                }
+                Spans::from(title)
+            })
+            .collect();
+        Tabs::new(titles)
+            .select(self.tab)
+            .style(self.styles.dim)
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Reset)
+                    .add_modifier(Modifier::BOLD),
+            )
+    }
+
+    /// Which tab shows the timeline of `kind`, if any.
+    fn tab_of(&self, kind: &TimelineKind) -> Option<usize> {
+        self.tab_timelines()
+            .position(|timeline| timeline.kind == *kind)
+    }
+
+    /// Polls the mentions timeline in the background, if notifications for
+    /// mentions are on, separately from whatever timeline is showing.
+    fn watch_mentions(&mut self) {
+        self.mentions_poller = None;
+        if !self.config.notifications.mentions || self.read_only() {
+            return;
+        }
+
+        let timeline = egg_mode::tweet::mentions_timeline(&self.token)
+            .with_page_size(self.config.ui.page_size() as i32);
+        let (requests, requests_rx) = mpsc::unbounded_channel();
+        let (results_tx, results) = mpsc::unbounded_channel();
+        let (busy, _) = watch::channel(false);
+        tokio::spawn(poll_timeline(
+            "mentions".to_string(),
+            Box::new(timeline),
+            self.config.ui.refresh_interval(),
+            self.config.ui.timeout(),
+            requests_rx,
+            results_tx,
+            busy,
+        ));
+        tokio::spawn(notify_mentions(results));
+        self.mentions_poller = Some(requests);
+    }
+
+    /// Finds out who the current account is, in the background. Bearer
+    /// tokens don't belong to anyone, so there's nothing to find.
+    fn look_up_user(&self) {
+        if self.read_only() {
+            return;
+        }
+        let account = self.account;
+        let token = self.token.clone();
+        let retries = Retries::new(&self.config.ui);
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            let result = with_retry(retries, "verify_tokens", || {
+                egg_mode::auth::verify_tokens(&token)
+            })
+            .await;
+            let _ = events.send(Event::Verified {
+                account,
+                result: result.map(|r| (r.response.id, r.response.screen_name)),
+            });
+        });
+    }
+
+    fn handle_event(&mut self, event: Event) -> Result<()> {
+        match event {
+            Event::Key(key) => match self.mode {
+                Mode::Normal => self.handle_key(key),
+                Mode::Prompt(_) => self.handle_prompt_key(key),
+                Mode::Compose(_) => self.handle_compose_key(key),
+                Mode::Help => self.mode = Mode::Normal,
+                Mode::Messages(_) => self.handle_messages_key(key),
+                Mode::Confirm(_) => self.handle_confirm_key(key),
+                Mode::Profile(_) => {
+                    if key == Key::Esc {
+                        self.mode = Mode::Normal;
+                    }
+                }
+            },
+            Event::Mouse(MouseEvent::Press(MouseButton::Left, x, y)) => {
+                if let Mode::Normal = self.mode {
+                    // termion counts from 1, tui from 0.
+                    self.click(x.saturating_sub(1), y.saturating_sub(1));
+                }
+            }
+            Event::Mouse(_) => {}
+            Event::Sent { from, result } => match result {
+                Ok(tweet) => {
+                    self.mode = Mode::Normal;
+                    self.remember(Done::Final("sending a tweet"));
+                    self.show_info("tweet sent");
+                    self.insert_sent(&from, vec![*tweet]);
+                }
+                Err(e) => {
+                    if let Mode::Compose(compose) = &mut self.mode {
+                        compose.sending = false;
+                    }
+                    self.show_error(format!("couldn't send tweet: {}", e));
+                }
+            },
+            Event::ThreadSent {
+                from,
+                sent,
+                unsent,
+                result,
+            } => {
+                let total = sent.len() + unsent.len();
+                let last = sent.last().map(|tweet| ReplyTo {
+                    id: tweet.id,
+                    screen_name: screen_name(tweet),
+                });
+                self.insert_sent(&from, sent);
+                match result {
+                    Ok(()) => {
+                        self.mode = Mode::Normal;
+                        self.remember(Done::Final("sending a thread"));
+                        self.show_info(format!("thread of {} tweets sent", total));
+                    }
+                    Err(e) => {
+                        // What's left carries on the thread from the last
+                        // tweet that made it, if any did.
+                        let failed = total - unsent.len() + 1;
+                        if let Mode::Compose(compose) = &mut self.mode {
+                            compose.sending = false;
+                            compose.text = unsent.join(&format!("\n{}\n", THREAD_DELIMITER));
+                            compose.numbered = true;
+                            if last.is_some() {
+                                compose.reply_to = last;
+                                compose.quote = None;
+                            }
+                        }
+                        self.show_error(format!(
+                            "sent {} of {} tweets, couldn't send tweet {}: {}",
+                            failed - 1,
+                            total,
+                            failed,
+                            e
+                        ));
+                    }
+                }
+            }
+            Event::UserTimeline(handle, result) => match result {
+                Ok((timeline, tweets)) => {
+                    self.set_timeline(TimelineRenderer::user(
+                        &handle,
+                        timeline,
+                        tweets,
+                        &self.config.ui,
+                        self.styles.clone(),
+                        self.mutes.clone(),
+                    ));
+                }
+                Err(e) => self.show_error(describe_user_error(&handle, &e)),
+            },
+            Event::Toggled {
+                toggle,
+                id,
+                on,
+                result,
+            } => match result {
+                Ok(response) => {
+                    for timeline in self.timelines() {
+                        timeline.update_tweet(id, |tweet| toggle.reconcile(tweet, &response, on));
+                    }
+                }
+                Err(e) => {
+                    for timeline in self.timelines() {
+                        timeline.update_tweet(id, |tweet| toggle.set(tweet, !on));
+                    }
+                    self.forget(toggle, id, on);
+                    self.show_error(format!("couldn't {} tweet: {}", toggle.verb(on), e));
+                }
+            },
+            Event::BatchToggled {
+                toggle,
+                on,
+                results,
+            } => {
+                let total = results.len();
+                let mut failure = None;
+                let mut failed = 0;
+                for (id, result) in results {
+                    match result {
+                        Ok(response) => {
+                            for timeline in self.timelines() {
+                                timeline.update_tweet(id, |tweet| {
+                                    toggle.reconcile(tweet, &response, on)
+                                });
+                            }
+                        }
+                        Err(e) => {
+                            for timeline in self.timelines() {
+                                timeline.update_tweet(id, |tweet| toggle.set(tweet, !on));
+                            }
+                            self.forget(toggle, id, on);
+                            failed += 1;
+                            failure.get_or_insert(e);
+                        }
+                    }
+                }
+                let summary = format!(
+                    "{} {} tweet{}",
+                    toggle.done(on),
+                    total - failed,
+                    if total - failed == 1 { "" } else { "s" }
+                );
+                match failure {
+                    Some(e) => self.show_error(format!("{}, {} failed: {}", summary, failed, e)),
+                    None => self.show_info(summary),
+                }
+            }
+            Event::Verified { account, result } => {
+                if account == self.account {
+                    match result {
+                        Ok((id, screen_name)) => {
+                            self.user_id = Some(id);
+                            self.screen_name = Some(screen_name);
+                        }
+                        // There's no point going on with a token that doesn't
+                        // work, so it's authorized again straight away.
+                        Err(e) if is_revoked(&e) => return Err(Error::Revoked(account)),
+                        Err(e) => self.show_error(format!("couldn't check who you are: {}", e)),
+                    }
+                }
+            }
+            Event::Deleted { id, result } => match result {
+                Ok(()) => {
+                    for timeline in self.timelines() {
+                        timeline.remove_tweet(id);
+                    }
+                    self.parents.remove(&id);
+                    self.remember(Done::Final("deleting a tweet"));
+                    self.show_info("tweet deleted");
+                }
+                Err(e) => self.show_error(format!("couldn't delete tweet: {}", e)),
+            },
+            Event::Profile { id, result } => match result {
+                Ok(user) => {
+                    self.profiles.insert(id, Some(user));
+                }
+                Err(e) => {
+                    self.profiles.remove(&id);
+                    if let Mode::Profile(showing) = self.mode {
+                        if showing == id {
+                            self.mode = Mode::Normal;
+                        }
+                    }
+                    self.show_error(format!("couldn't load profile: {}", e));
+                }
+            },
+            Event::Related {
+                account,
+                id,
+                result,
+            } => {
+                if account == self.account {
+                    match result {
+                        Ok(relation) => {
+                            self.relations.insert(id, Some(relation));
+                        }
+                        Err(e) => self.show_error(format!("couldn't check who you follow: {}", e)),
+                    }
+                }
+            }
+            Event::Followed {
+                account,
+                id,
+                handle,
+                follow,
+                result,
+            } => {
+                if account == self.account {
+                    match result {
+                        // Following a protected account only asks to.
+                        Ok(user) if follow && user.protected => {
+                            self.show_info(format!("asked to follow @{}", handle))
+                        }
+                        Ok(_) => {
+                            if let Some(Some(relation)) = self.relations.get_mut(&id) {
+                                relation.following = follow;
+                            }
+                            let verb = if follow { "followed" } else { "unfollowed" };
+                            self.show_info(format!("{} @{}", verb, handle));
+                        }
+                        Err(e) => {
+                            let verb = if follow { "follow" } else { "unfollow" };
+                            self.show_error(format!("couldn't {} @{}: {}", verb, handle, e));
+                        }
+                    }
+                }
+            }
+            Event::Messages(result) => match result {
+                Ok(conversations) => {
+                    if let Mode::Normal = self.mode {
+                        self.mode = Mode::Messages(Messages::new(conversations, &self.styles));
+                    }
+                }
+                // Apps need asking for permission to read direct messages
+                // separately from everything else.
+                Err(e) if has_error_code(&e, &[93]) => self.show_error(
+                    "twrs isn't allowed to read your direct messages, give your twitter app \
+                     permission to and authorize it again",
+                ),
+                Err(e) => self.show_error(format!("couldn't load direct messages: {}", e)),
+            },
+            // A thumbnail that can't be fetched is left out, like it would be
+            // on a terminal that can't show it.
+            Event::Thumbnail { url, result } => {
+                self.thumbnails.insert(url, result.ok());
+            }
+            Event::Opened(result) => {
+                if let Err(e) = result {
+                    self.show_error(format!("couldn't open browser: {}", e));
+                }
+            }
+            // Anything fetched after leaving the thread is only worth keeping
+            // for next time, and the walk up it stops there.
+            Event::Parent { thread, id, result } => {
+                let open = self.timeline.kind == TimelineKind::Thread(thread);
+                match result {
+                    Ok(parent) => {
+                        self.parents.insert(parent.id, (*parent).clone());
+                        if open {
+                            self.timeline.insert(vec![(*parent).clone()]);
+                            self.walk_thread(thread, *parent);
+                        }
+                    }
+                    Err(e) if open => {
+                        // Either way, this takes the place of saying it's
+                        // loading.
+                        let placeholder = match unavailable_placeholder(&e) {
+                            Some(placeholder) => {
+                                self.status = None;
+                                placeholder
+                            }
+                            None => {
+                                self.show_error(format!("couldn't load the whole thread: {}", e));
+                                "[unavailable]"
+                            }
+                        };
+                        self.timeline.missing_parent = Some((id, placeholder));
+                    }
+                    Err(_) => {}
+                }
+            }
+            Event::Error(e) => return Err(e),
+        }
+        Ok(())
+    }
+
+    fn handle_key(&mut self, key: Key) {
+        let keymap = &self.config.keymap;
+        if let Key::Char(c) = key {
+            if let Some(digit) = c.to_digit(10) {
+                // Digits only count when they aren't bound to anything.
+                if self.pending.is_empty() && keymap.action(&[key]).is_none() {
+                    let count = self.count.unwrap_or(0);
+                    self.count = Some(count.saturating_mul(10).saturating_add(digit as usize));
+                    return;
+                }
+            }
+        }
+
+        self.pending.push(key);
+        if keymap.is_prefix(&self.pending) {
+            return;
+        }
+        let action = keymap.action(&self.pending);
+        self.pending.clear();
+        // A count only applies to the motion straight after it.
+        let typed = self.count.take();
+        if let Some(action) = action {
+            self.perform(action, typed);
+        }
+    }
+
+    /// Does what `action` does, whether it came from a key or a command.
+    /// `typed` is the count typed before it, if there was one.
+    fn perform(&mut self, action: Action, typed: Option<usize>) {
+        let count = typed.unwrap_or(1);
+        match action {
+            _ if self.read_only() && needs_user(action).is_some() => {
+                let what = needs_user(action).unwrap();
+                self.show_error(Error::ReadOnly(what).to_string());
+            }
+            Action::Back if !self.timeline.marked.is_empty() => self.timeline.marked.clear(),
+            Action::Back if self.previous.is_some() => {
+                self.timeline = self.previous.take().unwrap();
+            }
+            Action::Quit | Action::Back => self.quit = true,
+            Action::ScrollDown => {
+                for _ in 0..count.min(self.timeline.listed().count()) {
+                    self.timeline.select_next();
+                }
+            }
+            Action::ScrollUp => {
+                for _ in 0..count.min(self.timeline.listed().count()) {
+                    self.timeline.select_previous();
+                }
+            }
+            // These go to the timeline's tab if it has one, and otherwise
+            // show it in this one.
+            Action::Home => match self.tab_of(&TimelineKind::Home) {
+                Some(i) => self.switch_tab(i),
+                None if self.timeline.kind != TimelineKind::Home => {
+                    self.set_timeline(App::home_timeline(
+                        &self.config,
+                        &self.token,
+                        &self.styles,
+                        &self.mutes,
+                    ));
+                }
+                None => {}
+            },
+            Action::Mentions => match self.tab_of(&TimelineKind::Mentions) {
+                Some(i) => self.switch_tab(i),
+                None if self.timeline.kind != TimelineKind::Mentions => {
+                    self.set_timeline(TimelineRenderer::mentions(
+                        &self.token,
+                        &self.config.ui,
+                        self.styles.clone(),
+                        self.mutes.clone(),
+                    ));
+                }
+                None => {}
+            },
+            Action::Thread => self.open_thread(),
+            Action::Compose => {
+                self.mode = Mode::Compose(Compose::new());
+            }
+            Action::Reply => {
+                if let Some(tweet) = self.timeline.selected() {
+                    self.mode = Mode::Compose(Compose::reply(tweet));
+                }
+            }
+            Action::Quote => {
+                if let Some(tweet) = self.timeline.selected() {
+                    self.mode = Mode::Compose(Compose::quote(tweet));
+                }
+            }
+            Action::SwitchAccount => self.switch_account(),
+            Action::Mute => self.mute_author(),
+            Action::Delete => self.confirm_delete(),
+            Action::Follow => self.confirm_follow(),
+            Action::Bookmark => self.toggle_bookmark(),
+            Action::NextByAuthor | Action::PreviousByAuthor => {
+                let down = action == Action::NextByAuthor;
+                if !self
+                    .timeline
+                    .select_by_author(down, self.config.ui.wrap_jumps())
+                {
+                    if let Some(author) = self.selected_author() {
+                        let message = format!(
+                            "no more tweets by @{} {}",
+                            author.screen_name,
+                            if down { "below" } else { "above" }
+                        );
+                        self.show_info(message);
+                    }
+                }
+            }
+            Action::NextTab => match typed {
+                Some(n) => self.switch_tab(n.saturating_sub(1)),
+                None => self.switch_tab((self.tab + 1) % self.tabs.len()),
+            },
+            Action::PreviousTab => {
+                self.switch_tab((self.tab + self.tabs.len() - 1) % self.tabs.len())
+            }
+            Action::Mark => self.timeline.toggle_mark(),
+            Action::MarkRead => self.timeline.mark_all_read(),
+            Action::Pin => match self.timeline.toggle_pin() {
+                Some(true) => self.show_info("tweet pinned"),
+                Some(false) => self.show_info("tweet unpinned"),
+                None => {}
+            },
+            Action::Bookmarks => match self.tab_of(&TimelineKind::Bookmarks) {
+                Some(i) => self.switch_tab(i),
+                None if self.timeline.kind == TimelineKind::Bookmarks => {}
+                None if self.bookmarks.is_empty() => self.show_info("no bookmarks yet"),
+                None => {
+                    self.set_timeline(TimelineRenderer::bookmarks(
+                        self.bookmarks.values().cloned().collect(),
+                        &self.config.ui,
+                        self.styles.clone(),
+                        self.mutes.clone(),
+                    ));
+                }
+            },
+            Action::Help => self.mode = Mode::Help,
+            Action::Profile => self.show_profile(),
+            Action::Pause => self.timeline.toggle_paused(),
+            Action::Top => self.timeline.select_newest(),
+            Action::Bottom => self.timeline.select_oldest(),
+            Action::Messages => self.load_messages(),
+            Action::Like => self.toggle(Toggle::Like),
+            Action::Retweet => self.toggle(Toggle::Retweet),
+            Action::Undo => self.undo(),
+            Action::Open if !self.timeline.marked.is_empty() => {
+                for tweet in self.timeline.marked_tweets() {
+                    let tweet = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+                    open_in_browser(tweet_url(tweet), self.events.clone());
+                }
+                self.timeline.marked.clear();
+            }
+            Action::Open => {
+                if let Some(tweet) = self.timeline.selected() {
+                    let tweet = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+                    open_in_browser(tweet_url(tweet), self.events.clone());
+                }
+            }
+            Action::CopyText => {
+                if let Some(tweet) = self.timeline.selected() {
+                    let tweet = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+                    let text = plain_text(tweet);
+                    self.copy(text, "text");
+                }
+            }
+            Action::CopyUrl => {
+                if let Some(tweet) = self.timeline.selected() {
+                    let tweet = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+                    let url = tweet_url(tweet);
+                    self.copy(url, "link");
+                }
+            }
+            Action::User => self.mode = Mode::Prompt(Prompt::new(PromptKind::User, "")),
+            Action::Search => self.mode = Mode::Prompt(Prompt::new(PromptKind::Search, "")),
+            Action::Export => self.mode = Mode::Prompt(Prompt::new(PromptKind::Export, "")),
+            Action::Command => self.mode = Mode::Prompt(Prompt::new(PromptKind::Command, "")),
+            Action::Popular => {
+                if let TimelineKind::Search { query, popular } = &self.timeline.kind {
+                    let search = TimelineRenderer::search(
+                        query,
+                        !popular,
+                        &self.token,
+                        &self.config.ui,
+                        self.styles.clone(),
+                        self.mutes.clone(),
+                    );
+                    self.set_timeline(search);
+                }
+            }
+        }
+    }
+
+    /// Loads direct messages in the background, to be shown once they're in.
+    fn load_messages(&mut self) {
+        // Which side of each conversation is ours depends on who we are.
+        let me = match self.user_id {
+            Some(id) => id,
+            None => {
+                return self
+                    .show_error("still checking which account this is, try again in a moment")
+            }
+        };
+        self.show_info("loading direct messages…");
+        let token = self.token.clone();
+        let retries = Retries::new(&self.config.ui);
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            let result = with_retry(retries, "conversations", || {
+                messages::conversations(me, &token)
+            })
+            .await;
+            let _ = events.send(Event::Messages(result));
+        });
+    }
+
+    fn handle_messages_key(&mut self, key: Key) {
+        let messages = match &mut self.mode {
+            Mode::Messages(messages) => messages,
+            _ => return,
+        };
+        let last = messages.lines.len().saturating_sub(1) as u16;
+        match self.config.keymap.action(&[key]) {
+            Some(Action::ScrollDown) => messages.scroll = (messages.scroll + 1).min(last),
+            Some(Action::ScrollUp) => messages.scroll = messages.scroll.saturating_sub(1),
+            Some(Action::Back) | Some(Action::Quit) | Some(Action::Messages) => {
+                self.mode = Mode::Normal
+            }
+            _ => {}
+        }
+    }
+
+    /// Deletes the selected tweet, if it's ours to delete, asking first unless
+    /// the config says not to.
+    fn confirm_delete(&mut self) {
+        let tweet = match self.timeline.selected() {
+            Some(tweet) => tweet,
+            None => return,
+        };
+        let author = tweet.user.as_ref().map(|user| user.id);
+        match self.user_id {
+            None => self.show_error("still checking which account this is, try again in a moment"),
+            Some(id) if author == Some(id) => {
+                let id = tweet.id;
+                self.confirm(Confirm::Delete(id));
+            }
+            Some(_) => self.show_error("you can only delete your own tweets"),
+        }
+    }
+
+    /// Does `confirm` straight away, or asks first if the config says to.
+    fn confirm(&mut self, confirm: Confirm) {
+        if confirm.wanted(&self.config.confirm) {
+            self.mode = Mode::Confirm(confirm);
+        } else {
+            self.carry_out(confirm);
+        }
+    }
+
+    fn handle_confirm_key(&mut self, key: Key) {
+        let confirm = match std::mem::replace(&mut self.mode, Mode::Normal) {
+            Mode::Confirm(confirm) => confirm,
+            mode => {
+                self.mode = mode;
+                return;
+            }
+        };
+        if key == Key::Char('y') {
+            self.carry_out(confirm);
+        }
+    }
+
+    fn carry_out(&mut self, confirm: Confirm) {
+        match confirm {
+            Confirm::Delete(id) => {
+                let token = self.token.clone();
+                let retries = Retries::new(&self.config.ui);
+                let events = self.events.clone();
+                tokio::spawn(async move {
+                    let result =
+                        with_retry(retries, "delete", || egg_mode::tweet::delete(id, &token))
+                            .await
+                            .map(|_| ());
+                    let _ = events.send(Event::Deleted { id, result });
+                });
+            }
+            Confirm::Follow { id, handle, follow } => {
+                let account = self.account;
+                let token = self.token.clone();
+                let retries = Retries::new(&self.config.ui);
+                let events = self.events.clone();
+                tokio::spawn(async move {
+                    let result = if follow {
+                        with_retry(retries, "follow", || {
+                            egg_mode::user::follow(id, false, &token)
+                        })
+                        .await
+                    } else {
+                        with_retry(retries, "unfollow", || egg_mode::user::unfollow(id, &token))
+                            .await
+                    };
+                    let _ = events.send(Event::Followed {
+                        account,
+                        id,
+                        handle,
+                        follow,
+                        result: result.map(|r| Box::new(r.response)),
+                    });
+                });
+            }
+            Confirm::Toggle { toggle, ids, on } => {
+                self.send_toggle(toggle, ids.clone(), on);
+                self.remember(Done::Toggle { toggle, ids, on });
+            }
+        }
+    }
+
+    /// Bookmarks the selected tweet, or removes its bookmark if it already
+    /// has one, and saves the bookmarks.
+    fn toggle_bookmark(&mut self) {
+        let tweet = match self.timeline.selected() {
+            Some(tweet) => tweet,
+            None => return,
+        };
+        let id = tweet.id;
+        let tweet = tweet.clone();
+        let removed = self.bookmarks.remove(&id).is_some();
+        if !removed {
+            self.bookmarks.insert(id, tweet.clone());
+        }
+        // Any bookmarks tab in the background is kept up to date too.
+        let others = self.tabs.iter_mut().flatten();
+        for timeline in std::iter::once(&mut self.timeline).chain(others) {
+            if timeline.kind == TimelineKind::Bookmarks {
+                if removed {
+                    timeline.remove_tweet(id);
+                } else {
+                    timeline.insert(vec![tweet.clone()]);
+                }
+            }
+        }
+        let message = if removed {
+            "bookmark removed"
+        } else {
+            "bookmarked"
+        };
+
+        let path = match &self.bookmarks_path {
+            Some(path) => path,
+            None => return self.show_error("couldn't find anywhere to save bookmarks"),
+        };
+        match bookmarks::save(path, self.bookmarks.values()) {
+            Ok(()) => self.show_info(message),
+            Err(e) => self.show_error(format!("couldn't save bookmarks: {}", e)),
+        }
+    }
+
+    /// Whether the account only has a bearer token, so it can read public
+    /// timelines but can't do anything as a user.
+    fn read_only(&self) -> bool {
+        matches!(self.token, egg_mode::Token::Bearer(_))
+    }
+
+    /// Who wrote the selected tweet, or the tweet it retweets.
+    fn selected_author(&self) -> Option<&TwitterUser> {
+        let tweet = self.timeline.selected()?;
+        let tweet = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+        tweet.user.as_deref()
+    }
+
+    /// Starts looking up how we relate to the selected tweet's author, unless
+    /// that's already known or on its way.
+    fn look_up_relation(&mut self) {
+        let me = match self.user_id {
+            Some(id) => id,
+            None => return,
+        };
+        let id = match self.selected_author() {
+            Some(author) if author.id != me => author.id,
+            _ => return,
+        };
+        if self.relations.contains_key(&id) {
+            return;
+        }
+        self.relations.insert(id, None);
+
+        let account = self.account;
+        let token = self.token.clone();
+        let retries = Retries::new(&self.config.ui);
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            let result = with_retry(retries, "relation", || {
+                egg_mode::user::relation(me, id, &token)
+            })
+            .await;
+            let _ = events.send(Event::Related {
+                account,
+                id,
+                result: result.map(|r| Relation {
+                    following: r.response.source.following,
+                    followed_by: r.response.source.followed_by,
+                }),
+            });
+        });
+    }
+
+    /// Shows the selected tweet's author's profile, fetching it first if it
+    /// hasn't been shown before.
+    fn show_profile(&mut self) {
+        let id = match self.selected_author() {
+            Some(author) => author.id,
+            None => return,
+        };
+        self.mode = Mode::Profile(id);
+        if self.profiles.contains_key(&id) {
+            return;
+        }
+        self.profiles.insert(id, None);
+
+        let token = self.token.clone();
+        let retries = Retries::new(&self.config.ui);
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            let result = with_retry(retries, "profile", || egg_mode::user::show(id, &token)).await;
+            let _ = events.send(Event::Profile {
+                id,
+                result: result.map(|r| Box::new(r.response)),
+            });
+        });
+    }
+
+    /// Follows the selected tweet's author, or unfollows them if they're
+    /// already followed, asking first if the config says to.
+    fn confirm_follow(&mut self) {
+        let author = match self.selected_author() {
+            Some(author) => author,
+            None => return,
+        };
+        if self.user_id == Some(author.id) {
+            return self.show_error("you can't follow yourself");
+        }
+        let (id, handle) = (author.id, author.screen_name.clone());
+        match self.relations.get(&id) {
+            Some(Some(relation)) => {
+                let follow = !relation.following;
+                self.confirm(Confirm::Follow { id, handle, follow });
+            }
+            _ => self.show_error(format!(
+                "don't know yet whether you follow @{}, try again in a moment",
+                handle
+            )),
+        }
+    }
+
+    /// Adds the selected tweet's author to the muted users, and saves the
+    /// config so they stay muted.
+    fn mute_author(&mut self) {
+        let tweet = match self.timeline.selected() {
+            Some(tweet) => tweet.retweeted_status.as_deref().unwrap_or(tweet),
+            None => return,
+        };
+        let handle = match handle(tweet) {
+            Some(handle) => handle.to_string(),
+            None => return self.show_error("twitter didn't say who wrote this tweet"),
+        };
+
+        self.config.filters.users.push(handle.clone());
+        match Mutes::new(&self.config.filters) {
+            Ok(mutes) => {
+                let mutes = Rc::new(mutes);
+                for timeline in self.timelines() {
+                    timeline.set_mutes(mutes.clone());
+                }
+                self.mutes = mutes;
+            }
+            Err(e) => {
+                self.config.filters.users.pop();
+                self.show_error(format!("couldn't mute @{}: {}", handle, e));
+                return;
+            }
+        }
+
+        // With --mock there may not be a config file, and making one with no
+        // accounts in it would stop twrs starting normally.
+        if self.config.fixture.is_some() {
+            return self.show_info(format!("muted @{} until twrs quits", handle));
+        }
+        let saved = toml::to_string_pretty(&self.config)
+            .map_err(Error::from)
+            .and_then(|config| Ok(std::fs::write(&self.config_path, config)?));
+        match saved {
+            Ok(()) => self.show_info(format!("muted @{}", handle)),
+            Err(e) => self.show_error(format!("muted @{}, but couldn't save it: {}", handle, e)),
+        }
+    }
+
+    /// Every open timeline: the one showing, the one a thread was opened
+    /// from, and those in the other tabs.
+    fn timelines(&mut self) -> impl Iterator<Item = &mut TimelineRenderer> {
+        std::iter::once(&mut self.timeline)
+            .chain(self.previous.iter_mut())
+            .chain(self.tabs.iter_mut().flatten())
+    }
+
+    /// Adds tweets just sent from the `from` timeline to the open timelines
+    /// they belong in, which are any home timeline, and the one a reply was
+    /// sent from.
+    fn insert_sent(&mut self, from: &TimelineKind, tweets: Vec<Tweet>) {
+        for timeline in self.timelines() {
+            let belongs: Vec<Tweet> = tweets
+                .iter()
+                .filter(|tweet| {
+                    timeline.kind == TimelineKind::Home
+                        || (timeline.kind == *from && tweet.in_reply_to_status_id.is_some())
+                })
+                .cloned()
+                .collect();
+            if !belongs.is_empty() {
+                timeline.insert(belongs);
+            }
+        }
+    }
+
+    /// Switches to `timeline`, forgetting about any timeline a thread was
+    /// opened from.
+    fn set_timeline(&mut self, timeline: TimelineRenderer) {
+        self.timeline = timeline;
+        self.previous = None;
+    }
+
+    /// Shows the conversation leading up to the selected tweet.
+    fn open_thread(&mut self) {
+        let tweet = match self.timeline.selected() {
+            Some(tweet) => tweet.retweeted_status.as_deref().unwrap_or(tweet),
+            None => return,
+        };
+        if tweet.in_reply_to_status_id.is_none() {
+            self.show_info("not a reply");
+            return;
+        }
+        let tweet = tweet.clone();
+        self.show_thread(tweet.clone());
+        self.walk_thread(tweet.id, tweet);
+    }
+
+    /// Follows `from` back to the tweet it replies to, and so on up to the
+    /// start of the thread opened from the tweet with the id `thread`, adding
+    /// each to it as it goes. Parents that have been seen before are taken
+    /// from `parents`; the first that hasn't is fetched, and the walk picks up
+    /// again when it arrives as an `Event::Parent`, as long as the thread is
+    /// still open by then.
+    fn walk_thread(&mut self, thread: u64, mut from: Tweet) {
+        let mut found = vec![];
+        while let Some(id) = from.in_reply_to_status_id {
+            match self.parents.get(&id) {
+                Some(parent) => {
+                    from = parent.clone();
+                    found.push(parent.clone());
+                }
+                None => {
+                    self.timeline.insert(found);
+                    // There's no telling how far up it goes until it ends.
+                    let loaded = self.timeline.tweets.len();
+                    self.show_info(format!("loading thread ({}/?)…", loaded + 1));
+                    let token = self.token.clone();
+                    let retries = Retries::new(&self.config.ui);
+                    let events = self.events.clone();
+                    tokio::spawn(async move {
+                        let result =
+                            with_retry(retries, "show", || egg_mode::tweet::show(id, &token)).await;
+                        let _ = events.send(Event::Parent {
+                            thread,
+                            id,
+                            result: result.map(|r| Box::new(r.response)),
+                        });
+                    });
+                    return;
+                }
+            }
+        }
+        self.timeline.insert(found);
+        self.status = None;
+    }
+
+    /// Replaces the timeline with a thread that starts out with just `tweet`,
+    /// keeping the timeline to go back to. The tweets it replies to are
+    /// added by `walk_thread`.
+    fn show_thread(&mut self, tweet: Tweet) {
+        let id = tweet.id;
+        let thread = TimelineRenderer::thread(
+            id,
+            vec![tweet],
+            &self.config.ui,
+            self.styles.clone(),
+            self.mutes.clone(),
+        );
+        let mut previous = std::mem::replace(&mut self.timeline, thread);
+        previous.cancel_prefetch();
+        // Opening a thread from within a thread goes back to where the first
+        // was opened from.
+        if self.previous.is_none() {
+            self.previous = Some(previous);
+        }
+    }
+
+    /// Moves on to the next account that has already been authorized, and
+    /// starts again from its home timeline. Accounts that haven't been
+    /// authorized are skipped, since the PIN flow can't run inside the UI.
+    fn switch_account(&mut self) {
+        let accounts = &self.config.accounts;
+        if accounts.len() < 2 {
+            return;
+        }
+
+        let next = (1..accounts.len())
+            .map(|offset| (self.account + offset) % accounts.len())
+            .find(|&i| accounts[i].saved_token().is_some());
+
+        match next {
+            Some(i) => {
+                let label = accounts[i].label(i);
+                self.account = i;
+                self.token = accounts[i].saved_token().unwrap();
+                self.user_id = None;
+                self.screen_name = None;
+                self.relations.clear();
+                self.history.clear();
+                self.look_up_user();
+                self.watch_mentions();
+                self.parents.clear();
+                self.open_tabs();
+                self.mode = Mode::Normal;
+                self.show_info(format!("switched to {}", label));
+            }
+            None => self.show_error(
+                "no other accounts are authorized yet, restart twrs and pick one to authorize it",
+            ),
+        }
+    }
+
+    /// Likes or retweets the selected tweet, or undoes it if it's already
+    /// been done. The change is shown straight away and reverted if Twitter
+    /// rejects it.
+    fn toggle(&mut self, toggle: Toggle) {
+        if !self.timeline.marked.is_empty() {
+            return self.toggle_marked(toggle);
+        }
+        let (id, on) = match self.timeline.selected() {
+            Some(tweet) => {
+                let tweet = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+                (tweet.id, !toggle.is_on(tweet))
+            }
+            None => return,
+        };
+        self.confirm(Confirm::Toggle {
+            toggle,
+            ids: vec![id],
+            on,
+        });
+    }
+
+    /// Likes or retweets all the marked tweets, or undoes it for all of them
+    /// if it's already been done to every one, then clears the marks. Like a
+    /// single toggle, the change is shown straight away, and reverted for any
+    /// that Twitter rejects.
+    fn toggle_marked(&mut self, toggle: Toggle) {
+        let originals: Vec<(u64, bool)> = self
+            .timeline
+            .marked_tweets()
+            .into_iter()
+            .map(|tweet| {
+                let tweet = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+                (tweet.id, toggle.is_on(tweet))
+            })
+            .collect();
+        self.timeline.marked.clear();
+        let on = !originals.iter().all(|&(_, is_on)| is_on);
+        let mut ids: Vec<u64> = originals
+            .into_iter()
+            .filter(|&(_, is_on)| is_on != on)
+            .map(|(id, _)| id)
+            .collect();
+        // A tweet and a retweet of it can both be marked.
+        ids.sort_unstable();
+        ids.dedup();
+        self.confirm(Confirm::Toggle { toggle, ids, on });
+    }
+
+    /// Turns `toggle` on or off for each of `ids`, showing it straight away
+    /// and putting it back for any that Twitter rejects. Every open timeline
+    /// is kept up to date, since the same tweet can be in more than one.
+    fn send_toggle(&mut self, toggle: Toggle, ids: Vec<u64>, on: bool) {
+        for timeline in self.timelines() {
+            for &id in &ids {
+                timeline.update_tweet(id, |tweet| toggle.set(tweet, on));
+            }
+        }
+
+        let token = self.token.clone();
+        let retries = Retries::new(&self.config.ui);
+        let events = self.events.clone();
+        match ids[..] {
+            [] => {}
+            [id] => {
+                tokio::spawn(async move {
+                    let result =
+                        with_retry(retries, toggle.verb(on), || toggle.send(id, on, &token)).await;
+                    let _ = events.send(Event::Toggled {
+                        toggle,
+                        id,
+                        on,
+                        result: result.map(|r| Box::new(r.response)),
+                    });
+                });
+            }
+            _ => {
+                tokio::spawn(send_batch(toggle, ids, on, token, retries, events));
+            }
+        }
+    }
+
+    fn remember(&mut self, done: Done) {
+        if self.history.len() == HISTORY_LENGTH {
+            self.history.remove(0);
+        }
+        self.history.push(done);
+    }
+
+    /// Takes back the last like or retweet, or the last of them done to
+    /// marked tweets all at once. Anything Twitter can't take back is only
+    /// said so, and taken off the history.
+    fn undo(&mut self) {
+        match self.history.pop() {
+            None => self.show_info("nothing to undo"),
+            Some(Done::Final(what)) => self.show_error(format!("can't undo {}", what)),
+            Some(Done::Toggle { toggle, ids, on }) => {
+                self.show_info(format!("undoing {}", toggle.verb(on)));
+                self.send_toggle(toggle, ids, !on);
+            }
+        }
+    }
+
+    /// Forgets having turned `toggle` on or off for `id` when Twitter didn't
+    /// let it happen, since there's nothing to undo.
+    fn forget(&mut self, toggle: Toggle, id: u64, on: bool) {
+        for done in &mut self.history {
+            if let Done::Toggle {
+                toggle: t,
+                ids,
+                on: o,
+            } = done
+            {
+                if *t == toggle && *o == on {
+                    ids.retain(|&other| other != id);
+                }
+            }
+        }
+        self.history
+            .retain(|done| !matches!(done, Done::Toggle { ids, .. } if ids.is_empty()));
+    }
+
+    fn handle_prompt_key(&mut self, key: Key) {
+        let prompt = match &mut self.mode {
+            Mode::Prompt(prompt) => prompt,
+            _ => return,
+        };
+
+        prompt.candidates.clear();
+        match key {
+            Key::Esc => self.mode = Mode::Normal,
+            Key::Char('\n') => {
+                if let Mode::Prompt(prompt) = std::mem::replace(&mut self.mode, Mode::Normal) {
+                    self.submit_prompt(prompt);
+                }
+            }
+            Key::Char('\t') => {
+                if let PromptKind::Command = prompt.kind {
+                    prompt.complete_command();
+                }
+            }
+            Key::Backspace => {
+                prompt.input.pop();
+            }
+            Key::Char(c) => prompt.input.push(c),
+            _ => {}
+        }
+    }
+
+    fn handle_compose_key(&mut self, key: Key) {
+        let compose = match &mut self.mode {
+            Mode::Compose(compose) if !compose.sending => compose,
+            _ => return,
+        };
+
+        match key {
+            Key::Esc => self.mode = Mode::Normal,
+            Key::Ctrl('s') => {
+                let mut tweets = compose.tweets();
+                if tweets.is_empty() {
+                    return;
+                }
+                compose.sending = true;
+
+                let reply_to = compose.reply_to.as_ref().map(|reply_to| reply_to.id);
+                let quote = compose.quote.as_ref().map(|quote| quote.url.clone());
+                let from = self.timeline.kind.clone();
+                let token = self.token.clone();
+                let retries = Retries::new(&self.config.ui);
+                let events = self.events.clone();
+                if tweets.len() == 1 {
+                    let draft = DraftTweet::new(tweets.remove(0));
+                    let draft = match reply_to {
+                        Some(id) => draft.in_reply_to(id),
+                        None => draft,
+                    };
+                    let draft = match quote {
+                        Some(url) => draft.attachment_url(url),
+                        None => draft,
+                    };
+                    tokio::spawn(async move {
+                        let result = with_retry(retries, "tweet", || draft.send(&token))
+                            .await
+                            .map(|r| Box::new(r.response));
+                        let _ = events.send(Event::Sent { from, result });
+                    });
+                } else {
+                    let thread = send_thread(tweets, reply_to, quote, from, token, retries, events);
+                    tokio::spawn(thread);
+                }
+            }
+            Key::Backspace => {
+                compose.text.pop();
+            }
+            Key::Char(c) => compose.text.push(c),
+            _ => {}
+        }
+    }
+
+    fn submit_prompt(&mut self, prompt: Prompt) {
+        let input = prompt.input.trim();
+        if input.is_empty() {
+            return;
+        }
+
+        match prompt.kind {
+            PromptKind::User => {
+                let handle = input.trim_start_matches('@').to_string();
+                self.show_user(handle);
+            }
+            PromptKind::Search => {
+                let search = TimelineRenderer::search(
+                    input,
+                    false,
+                    &self.token,
+                    &self.config.ui,
+                    self.styles.clone(),
+                    self.mutes.clone(),
+                );
+                self.set_timeline(search);
+            }
+            PromptKind::Export => self.export(input),
+            PromptKind::Command => {
+                let (name, argument) = match input.split_once(' ') {
+                    Some((name, argument)) => (name, argument.trim()),
+                    None => (input, ""),
+                };
+                match (Action::named(name), argument.is_empty()) {
+                    (Some(action), true) => self.perform(action, None),
+                    (Some(Action::User), false) => {
+                        self.submit_prompt(Prompt::new(PromptKind::User, argument))
+                    }
+                    (Some(Action::Search), false) => {
+                        self.submit_prompt(Prompt::new(PromptKind::Search, argument))
+                    }
+                    (Some(Action::Export), false) => {
+                        self.submit_prompt(Prompt::new(PromptKind::Export, argument))
+                    }
+                    (Some(_), false) => {
+                        self.show_error(format!("{} doesn't take anything after it", name))
+                    }
+                    (None, _) => self.show_error(format!("there's no command called {:?}", name)),
+                }
+            }
+        }
+    }
+
+    /// Writes the tweets loaded in the timeline to the file at `input`, as
+    /// JSON or CSV depending on how its name ends.
+    fn export(&mut self, input: &str) {
+        let path = export::expand(input);
+        let format = match export::Format::of(&path) {
+            Some(format) => format,
+            None => return self.show_error("export to a file ending in .json or .csv"),
+        };
+        let tweets: Vec<&Tweet> = self.timeline.listed().collect();
+        let count = tweets.len();
+        match export::save(&path, format, tweets) {
+            Ok(()) => self.show_info(format!(
+                "exported {} tweet{} to {}",
+                count,
+                if count == 1 { "" } else { "s" },
+                path.display()
+            )),
+            Err(e) => self.show_error(format!("couldn't export to {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Follows the link at `x`, `y`, if there's one there.
+    fn click(&mut self, x: u16, y: u16) {
+        match self.timeline.link_at(x, y).cloned() {
+            Some(Link::Url(url)) => open_in_browser(url, self.events.clone()),
+            Some(Link::Mention(handle)) => self.show_user(handle),
+            None => {}
+        }
+    }
+
+    /// Switches to `handle`'s timeline once its first page has loaded.
+    fn show_user(&mut self, handle: String) {
+        self.show_info(format!("loading @{}…", handle));
+        tokio::spawn(load_user_timeline(
+            handle,
+            self.token.clone(),
+            self.config.ui.page_size(),
+            Retries::new(&self.config.ui),
+            self.events.clone(),
+        ));
+    }
+
+    /// Copies `text`, described by `what`, to the clipboard.
+    fn copy(&mut self, text: String, what: &str) {
+        let result = match self.clipboard.as_mut() {
+            Some(clipboard) => clipboard.set_text(text),
+            None => arboard::Clipboard::new().and_then(|mut clipboard| {
+                let result = clipboard.set_text(text);
+                self.clipboard = Some(clipboard);
+                result
+            }),
+        };
+        match result {
+            Ok(()) => self.show_info(format!("copied {}", what)),
+            Err(e) => self.show_error(format!("couldn't copy {}: {}", what, e)),
+        }
+    }
+
+    fn show_info<S: Into<String>>(&mut self, text: S) {
+        self.status = Some(Status {
+            text: text.into(),
+            is_error: false,
+            shown_at: Instant::now(),
+        });
+    }
+
+    fn show_error<S: Into<String>>(&mut self, text: S) {
+        let text = text.into();
+        tracing::warn!("showed error: {}", text);
+        self.status = Some(Status {
+            text,
+            is_error: true,
+            shown_at: Instant::now(),
+        });
+    }
+
+    /// Draws the selected tweet's thumbnail where the detail pane left room
+    /// for it, starting to fetch it if that hasn't been done yet. tui can't
+    /// draw images, so this writes straight to the terminal after a frame's
+    /// been drawn, and only when the thumbnail or where it goes has changed.
+    fn draw_thumbnail<B: Backend + Write>(&mut self, terminal: &mut Terminal<B>) -> Result<()> {
+        let protocol = match self.styles.thumbnails {
+            Some(protocol) => protocol,
+            None => return Ok(()),
+        };
+        // Anything drawn over the timeline would be drawn under the image.
+        let wanted = match self.mode {
+            Mode::Normal => self.timeline.thumbnail.clone(),
+            _ => None,
+        };
+        if let Some((_, url)) = &wanted {
+            if !self.thumbnails.contains_key(url) {
+                self.thumbnails.insert(url.clone(), None);
+                let url = url.clone();
+                let token = self.token.clone();
+                let timeout = self.config.ui.timeout();
+                let events = self.events.clone();
+                tokio::spawn(async move {
+                    let result = with_timeout(timeout, images::fetch(url.clone(), token)).await;
+                    let _ = events.send(Event::Thumbnail { url, result });
+                });
+            }
+        }
+        let wanted = wanted.filter(|(_, url)| matches!(self.thumbnails.get(url), Some(Some(_))));
+        if wanted == self.thumbnail_shown {
+            return Ok(());
+        }
+
+        if self.thumbnail_shown.take().is_some() {
+            match images::clear(protocol) {
+                Some(clear) => write!(terminal.backend_mut(), "{}", clear)?,
+                // Otherwise it only goes once the cells under it are drawn
+                // again, and tui only draws cells that have changed.
+                None => {
+                    terminal.clear()?;
+                    terminal.draw(|f| self.draw(f))?;
+                }
+            }
+        }
+        if let Some((area, url)) = &wanted {
+            let png = self.thumbnails[url].as_deref().unwrap_or_default();
+            let (columns, rows) = images::fit(png, area.width, area.height);
+            write!(
+                terminal.backend_mut(),
+                "{}{}",
+                cursor::Goto(area.x + 1, area.y + 1),
+                images::draw(protocol, png, columns, rows)
+            )?;
+        }
+        Write::flush(terminal.backend_mut())?;
+        self.thumbnail_shown = wanted;
+        Ok(())
+    }
+
+    fn draw<B: Backend>(&mut self, f: &mut Frame<B>) {
+        self.frame = self.frame.wrapping_add(1);
+
+        let compose_height = match &self.mode {
+            // Room for the text, plus a border on either side.
+            Mode::Compose(compose) => {
+                let width = f.size().width.saturating_sub(2) as usize;
+                let lines = wrap_chars(&compose.text, width, self.styles.emoji_width);
+                let preview = if compose.quote.is_some() {
+                    QUOTE_HEIGHT
+                } else {
+                    0
+                };
+                (lines.len() as u16 + 2).min(COMPOSE_MAX_HEIGHT) + preview
+            }
+            _ => 0,
+        };
+
+        let chunks = Layout::default()
+            .constraints(
+                [
+                    Constraint::Min(0),
+                    Constraint::Length(compose_height),
+                    Constraint::Length(1),
+                ]
+                .as_ref(),
+            )
+            .split(f.size());
+
+        // With more than one tab, they're listed above the timeline.
+        let tab_bar = (self.tabs.len() > 1) as u16;
+        let area = Rect {
+            y: chunks[0].y + tab_bar,
+            height: chunks[0].height.saturating_sub(tab_bar),
+            ..chunks[0]
+        };
+        if tab_bar > 0 {
+            f.render_widget(
+                self.tab_bar(),
+                Rect {
+                    height: 1,
+                    ..chunks[0]
+                },
+            );
+        }
+
+        self.timeline.me = self.user_id;
+        self.timeline.unread = 0;
+        self.timeline.relation = self
+            .selected_author()
+            .and_then(|author| self.relations.get(&author.id).copied().flatten());
+        f.render_widget(&mut self.timeline, area);
+
+        let label = match (&self.screen_name, self.config.accounts.len() > 1) {
+            (Some(screen_name), false) => Some(format!("logged in as @{}", screen_name)),
+            (Some(screen_name), true) => Some(format!(
+                "logged in as @{} ({})",
+                screen_name,
+                self.config.accounts[self.account].label(self.account)
+            )),
+            (None, true) => Some(self.config.accounts[self.account].label(self.account)),
+            (None, false) => None,
+        };
+        if let Some(label) = label {
+            let header = Rect { height: 1, ..area };
+            let label =
+                Paragraph::new(Span::styled(label, self.styles.dim)).alignment(Alignment::Right);
+            f.render_widget(label, header);
+        }
+
+        if let Mode::Compose(compose) = &self.mode {
+            draw_compose(f, compose, &self.styles, chunks[1]);
+        }
+
+        match &self.mode {
+            Mode::Prompt(prompt) => {
+                let label = prompt.kind.label();
+                let mut line = vec![
+                    Span::styled(label, Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(prompt.input.clone()),
+                ];
+                if !prompt.candidates.is_empty() {
+                    line.push(Span::styled(
+                        format!("  {}", prompt.candidates.join(" ")),
+                        self.styles.dim,
+                    ));
+                }
+                let line = Spans::from(line);
+                f.render_widget(Paragraph::new(line), chunks[2]);
+
+                let x = chunks[2].x + (label.width() + prompt.input.width()) as u16;
+                f.set_cursor(x.min(chunks[2].right().saturating_sub(1)), chunks[2].y);
+            }
+            Mode::Confirm(confirm) => {
+                let mut line = vec![
+                    Span::styled(
+                        confirm.question(),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw("  "),
+                ];
+                line.extend(hint_line(&[("y", "yes"), ("any other key", "no")], &self.styles).0);
+                f.render_widget(Paragraph::new(Spans::from(line)), chunks[2]);
+            }
+            _ => {
+                let mut right = vec![];
+                if self.timeline.is_busy() {
+                    let frame = SPINNER[self.frame % SPINNER.len()];
+                    right.push(Span::styled(frame, self.styles.dim));
+                } else if self.timeline.paused {
+                    right.push(Span::styled("⏸ paused", self.styles.warning));
+                } else if self.timeline.retrying {
+                    right.push(Span::styled(
+                        "connection error, retrying…",
+                        self.styles.warning,
+                    ));
+                } else if let Some(updated_at) = self.timeline.updated_at {
+                    right.push(Span::styled(
+                        format!("updated {} ago", describe_age(updated_at.elapsed())),
+                        self.styles.dim,
+                    ));
+                }
+                if let Some(rate_limit) = &self.timeline.rate_limit {
+                    if !right.is_empty() {
+                        right.push(Span::raw("  "));
+                    }
+                    right.push(describe_rate_limit(rate_limit, &self.styles));
+                }
+                let right = Spans::from(right);
+                let status_chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(
+                        [Constraint::Min(0), Constraint::Length(right.width() as u16)].as_ref(),
+                    )
+                    .split(chunks[2]);
+
+                f.render_widget(Paragraph::new(right), status_chunks[1]);
+
+                let line = match &self.status {
+                    Some(status) if status.shown_at.elapsed() < STATUS_TIMEOUT => {
+                        let style = if status.is_error {
+                            self.styles.error
+                        } else {
+                            self.styles.dim
+                        };
+                        Spans::from(Span::styled(status.text.clone(), style))
+                    }
+                    _ => match self.mode {
+                        Mode::Normal => {
+                            hint_line(&action_hints(&self.config.keymap, HINTS), &self.styles)
+                        }
+                        Mode::Help => hint_line(&[("any key", "close")], &self.styles),
+                        Mode::Messages(_) => hint_line(
+                            &action_hints(&self.config.keymap, MESSAGES_HINTS),
+                            &self.styles,
+                        ),
+                        Mode::Profile(_) => hint_line(&[("esc", "close")], &self.styles),
+                        _ => Spans::default(),
+                    },
+                };
+                f.render_widget(Paragraph::new(line), status_chunks[0]);
+            }
+        }
+
+        match &self.mode {
+            Mode::Help => draw_help(f, &self.config.keymap, chunks[0]),
+            Mode::Messages(messages) => draw_messages(f, messages, chunks[0]),
+            Mode::Profile(id) => {
+                let user = self.profiles.get(id).and_then(Option::as_deref);
+                let relation = if Some(*id) == self.user_id {
+                    Some("this is you")
+                } else {
+                    self.relations
+                        .get(id)
+                        .copied()
+                        .flatten()
+                        .map(|relation| relation.describe())
+                };
+                draw_profile(f, user, relation, &self.styles, chunks[0]);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `hints`, like `HINTS`, for the keys in `keymap`, leaving out any that
+/// aren't bound.
+fn action_hints(
+    keymap: &Keymap,
+    hints: &[(&[Action], &'static str)],
+) -> Vec<(String, &'static str)> {
+    hints
+        .iter()
+        .filter_map(|(actions, hint)| {
+            let keys: Option<Vec<String>> =
+                actions.iter().map(|action| keymap.key(*action)).collect();
+            Some((keys?.join("/"), *hint))
+        })
+        .collect()
+}
+
+/// Lays out key hints like "j/k move  ? help".
+fn hint_line<K: AsRef<str>>(hints: &[(K, &str)], styles: &Styles) -> Spans<'static> {
+    let mut spans = vec![];
+    for (key, action) in hints {
+        if !spans.is_empty() {
+            spans.push(Span::raw("  "));
+        }
+        spans.push(Span::styled(
+            key.as_ref().to_string(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::styled(format!(" {}", action), styles.dim));
+    }
+    Spans::from(spans)
+}
+
+/// Draws every key binding in `keymap` in a box in the middle of `area`.
+fn draw_help<B: Backend>(f: &mut Frame<B>, keymap: &Keymap, area: Rect) {
+    let mut bindings: Vec<(String, &str)> = ACTIONS
+        .iter()
+        .map(|(action, _, _, description)| (keymap.describe(*action), *description))
+        .filter(|(keys, _)| !keys.is_empty())
+        .collect();
+    bindings.push(("0-9".to_string(), "move that many tweets, like 5j"));
+    bindings.push(("click".to_string(), "open a link or mention"));
+
+    let key_width = bindings
+        .iter()
+        .map(|(key, _)| key.width())
+        .max()
+        .unwrap_or(0);
+    let lines: Vec<Spans> = bindings
+        .iter()
+        .map(|(key, action)| {
+            Spans::from(vec![
+                Span::styled(
+                    format!(" {:width$}  ", key, width = key_width),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(action.to_string()),
+            ])
+        })
+        .collect();
+
+    let width = lines.iter().map(Spans::width).max().unwrap_or(0) as u16 + 3;
+    let help = popup(area, width, lines.len() as u16 + 2);
+
+    f.render_widget(Clear, help);
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" keys ")),
+        help,
+    );
+}
+
+/// The most columns a profile popup takes up.
+const PROFILE_WIDTH: u16 = 60;
+
+/// Draws `user`'s profile in a popup over `area`, or says it's on its way
+/// while `user` is `None`. `relation` says how whoever's signed in follows
+/// them, if that's known.
+fn draw_profile<B: Backend>(
+    f: &mut Frame<B>,
+    user: Option<&TwitterUser>,
+    relation: Option<&str>,
+    styles: &Styles,
+    area: Rect,
+) {
+    let dim = styles.dim;
+    // Room for the borders and a column of padding on either side.
+    let width = PROFILE_WIDTH.min(area.width);
+    let text_width = width.saturating_sub(4) as usize;
+
+    let user = match user {
+        Some(user) => user,
+        None => {
+            let profile = popup(area, width, 3);
+            f.render_widget(Clear, profile);
+            f.render_widget(
+                Paragraph::new(Span::styled(" loading…", dim))
+                    .block(Block::default().borders(Borders::ALL).title(" profile ")),
+                profile,
+            );
+            return;
+        }
+    };
+
+    let mut name = vec![
+        Span::styled(
+            user.name.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(format!(" @{}", user.screen_name), dim),
+    ];
+    name.extend(user_badges(user, styles));
+    let mut lines = vec![Spans::from(name)];
+    if let Some(relation) = relation {
+        lines.push(Spans::from(Span::styled(relation, dim)));
+    }
+
+    let bio = LinkedText::bio(user).text;
+    if !bio.trim().is_empty() {
+        lines.push(Spans::default());
+        for words in wrap(&bio, text_width, styles.emoji_width) {
+            let line: Vec<&str> = words.iter().map(|word| &bio[word.clone()]).collect();
+            lines.push(Spans::from(line.join(" ")));
+        }
+    }
+
+    lines.push(Spans::default());
+    lines.push(Spans::from(vec![
+        Span::styled(
+            user.followers_count.to_string(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(if user.followers_count == 1 {
+            " follower  "
+        } else {
+            " followers  "
+        }),
+        Span::styled(
+            user.friends_count.to_string(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(" following"),
+    ]));
+    if let Some(location) = user.location.as_deref().filter(|l| !l.trim().is_empty()) {
+        lines.push(Spans::from(Span::styled(format!("📍 {}", location), dim)));
+    }
+    lines.push(Spans::from(Span::styled(
+        format!(
+            "joined {}",
+            styles.zone.convert(user.created_at).format("%B %Y")
+        ),
+        dim,
+    )));
+
+    // Each line is indented by a column inside the border.
+    let lines: Vec<Spans> = lines
+        .into_iter()
+        .map(|line| {
+            let mut spans = vec![Span::raw(" ")];
+            spans.extend(line.0);
+            Spans::from(spans)
+        })
+        .collect();
+    let profile = popup(area, width, lines.len() as u16 + 2);
+    f.render_widget(Clear, profile);
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" profile ")),
+        profile,
+    );
+}
+
+/// A `width` by `height` box in the middle of `area`, shrunk to fit.
+fn popup(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Draws direct messages over the whole of `area`.
+fn draw_messages<B: Backend>(f: &mut Frame<B>, messages: &Messages, area: Rect) {
+    let paragraph = Paragraph::new(messages.lines.clone())
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" direct messages "),
+        )
+        .wrap(Wrap { trim: false })
+        .scroll((messages.scroll, 0));
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+/// The frames of the spinner shown while a fetch is in progress, one per
+/// redraw.
+const SPINNER: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// A rough, short description of how long ago something was, like "3s".
+fn describe_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        _ => format!("{}h", secs / 3600),
+    }
+}
+
+/// How many calls are left before we're rate limited, or how long until we
+/// can make calls again if we already are. The poller waits until then by
+/// itself, so this counts down to it.
+fn describe_rate_limit(rate_limit: &RateLimit, styles: &Styles) -> Span<'static> {
+    if rate_limit.remaining <= 0 {
+        let left = i64::from(rate_limit.reset) - Utc::now().timestamp();
+        let text = if left > 0 {
+            format!("rate limited — resuming in {}:{:02}", left / 60, left % 60)
+        } else {
+            // The limit's reset, but nothing's come back since.
+            "rate limited — resuming…".to_string()
+        };
+        Span::styled(text, styles.warning)
+    } else {
+        let reset = styles
+            .zone
+            .convert(Utc.timestamp(i64::from(rate_limit.reset), 0))
+            .format("%H:%M:%S");
+        Span::styled(
+            format!(
+                "{}/{} calls left, resets {}",
+                rate_limit.remaining, rate_limit.limit, reset
+            ),
+            styles.dim,
+        )
+    }
+}
+
+/// The tallest the compose box gets before its text starts scrolling.
+const COMPOSE_MAX_HEIGHT: u16 = 10;
+
+/// How tall the preview of a quoted tweet under the compose box is, with its
+/// border.
+const QUOTE_HEIGHT: u16 = 4;
+
+/// Draws the compose box, with a running count of how much of the length
+/// limit has been used. Only the last lines are shown if the text is too long
+/// to fit, since that's where the cursor is.
+fn draw_compose<B: Backend>(f: &mut Frame<B>, compose: &Compose, styles: &Styles, area: Rect) {
+    let emoji = styles.emoji_width;
+    let area = match &compose.quote {
+        Some(quote) => {
+            let height = QUOTE_HEIGHT.min(area.height);
+            let preview = Rect {
+                y: area.bottom() - height,
+                height,
+                ..area
+            };
+            draw_quote(f, quote, styles, preview);
+            Rect {
+                height: area.height - height,
+                ..area
+            }
+        }
+        None => area,
+    };
+
+    // Anything that needs splitting up is counted in tweets instead.
+    let tweets = compose.tweets().len();
+    let counter = if tweets > 1 {
+        format!("thread of {} ", tweets)
+    } else {
+        format!("{}/{} ", weighted_length(&compose.text), MAX_TWEET_LENGTH)
+    };
+    let counter_style = styles.dim;
+
+    let title = if compose.sending {
+        "sending…".to_string()
+    } else {
+        let what = match (&compose.reply_to, &compose.quote) {
+            (Some(reply_to), _) => format!("reply to @{}", reply_to.screen_name),
+            (None, Some(quote)) => format!("quote @{}", quote.screen_name),
+            (None, None) => "new tweet".to_string(),
+        };
+        format!("{} (ctrl-s to send, esc to cancel)", what)
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Spans::from(vec![
+            Span::raw(format!(" {} ", title)),
+            Span::styled(counter, counter_style),
+        ]));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let lines = wrap_chars(&compose.text, inner.width as usize, emoji);
+    let visible = &lines[lines.len().saturating_sub(inner.height as usize)..];
+    let text: Vec<Spans> = visible.iter().map(|l| Spans::from(l.clone())).collect();
+    f.render_widget(Paragraph::new(text), inner);
+
+    if let Some(last) = visible.last() {
+        let x = inner.x + text_width(last, emoji) as u16;
+        let y = inner.y + visible.len() as u16 - 1;
+        f.set_cursor(x.min(inner.right().saturating_sub(1)), y);
+    }
+}
+
+/// The tweet being quoted, dimmed, for as much of it as fits.
+fn draw_quote<B: Backend>(f: &mut Frame<B>, quote: &Quote, styles: &Styles, area: Rect) {
+    let style = styles.dim;
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(style)
+        .title(Span::styled(format!(" @{} ", quote.screen_name), style));
+    let text = Paragraph::new(Span::styled(quote.text.clone(), style))
+        .block(block)
+        .wrap(Wrap { trim: true });
+    f.render_widget(text, area);
+}
+
+/// Splits `text` into lines no wider than `width` columns without regard for
+/// word boundaries, and without dropping any whitespace. This is what the
+/// compose box wants: every character typed stays where the cursor left it.
+/// `emoji` is as for `Columns`.
+fn wrap_chars(text: &str, width: usize, emoji: Option<usize>) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut line_width = 0;
+        let mut columns = Columns::new(emoji);
+        for c in paragraph.chars() {
+            let char_width = columns.width(c);
+            if line_width + char_width > width {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0;
+            }
+            line.push(c);
+            line_width += char_width;
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// How long Twitter considers `text` to be. Most Latin and punctuation
+/// characters count once and everything else, including CJK and emoji, counts
+/// twice. URLs always count as `URL_LENGTH` because Twitter shortens them.
+fn weighted_length(text: &str) -> usize {
+    let separators: usize = text
+        .chars()
+        .filter(|c| c.is_whitespace())
+        .map(char_weight)
+        .sum();
+    let words: usize = text
+        .split_whitespace()
+        .map(|word| {
+            if word.starts_with("http://") || word.starts_with("https://") {
+                URL_LENGTH
+            } else {
+                word.chars().map(char_weight).sum()
+            }
+        })
+        .sum();
+
+    separators + words
+}
+
+/// The length every URL counts as, whatever its actual length.
+const URL_LENGTH: usize = 23;
+
+/// The weight of a single character, following the ranges in twitter-text's
+/// v3 configuration.
+fn char_weight(c: char) -> usize {
+    match c as u32 {
+        0..=4351 | 8192..=8205 | 8208..=8223 | 8242..=8247 => 1,
+        _ => 2,
+    }
+}
+
+/// Something that can be done to a tweet and then undone again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Toggle {
+    Like,
+    Retweet,
+}
+
+impl Toggle {
+    fn is_on(self, tweet: &Tweet) -> bool {
+        match self {
+            Toggle::Like => tweet.favorited == Some(true),
+            Toggle::Retweet => tweet.retweeted == Some(true),
+        }
+    }
+
+    /// Turns the toggle on or off for `tweet`, adjusting its count to match.
+    fn set(self, tweet: &mut Tweet, on: bool) {
+        if self.is_on(tweet) == on {
+            return;
+        }
+        let delta = if on { 1 } else { -1 };
+        match self {
+            Toggle::Like => {
+                tweet.favorited = Some(on);
+                tweet.favorite_count += delta;
+            }
+            Toggle::Retweet => {
+                tweet.retweeted = Some(on);
+                tweet.retweet_count += delta;
+            }
+        }
+    }
+
+    /// Brings `tweet` in line with what Twitter sent back. Retweeting responds
+    /// with the new retweet rather than the original, so the original's
+    /// counts are taken from inside it.
+    fn reconcile(self, tweet: &mut Tweet, response: &Tweet, on: bool) {
+        let response = response.retweeted_status.as_deref().unwrap_or(response);
+        match self {
+            Toggle::Like => {
+                tweet.favorited = Some(on);
+                tweet.favorite_count = response.favorite_count;
+            }
+            Toggle::Retweet => {
+                tweet.retweeted = Some(on);
+                tweet.retweet_count = response.retweet_count;
+            }
+        }
+    }
+
+    fn verb(self, on: bool) -> &'static str {
+        match (self, on) {
+            (Toggle::Like, true) => "like",
+            (Toggle::Like, false) => "unlike",
+            (Toggle::Retweet, true) => "retweet",
+            (Toggle::Retweet, false) => "unretweet",
+        }
+    }
+
+    fn done(self, on: bool) -> &'static str {
+        match (self, on) {
+            (Toggle::Like, true) => "liked",
+            (Toggle::Like, false) => "unliked",
+            (Toggle::Retweet, true) => "retweeted",
+            (Toggle::Retweet, false) => "unretweeted",
+        }
+    }
+
+    async fn send(
+        self,
+        id: u64,
+        on: bool,
+        token: &egg_mode::Token,
+    ) -> egg_mode::error::Result<egg_mode::Response<Tweet>> {
+        match (self, on) {
+            (Toggle::Like, true) => egg_mode::tweet::like(id, token).await,
+            (Toggle::Like, false) => egg_mode::tweet::unlike(id, token).await,
+            (Toggle::Retweet, true) => egg_mode::tweet::retweet(id, token).await,
+            (Toggle::Retweet, false) => egg_mode::tweet::unretweet(id, token).await,
+        }
+    }
+}
+
+/// How many of a batch of toggles are sent at once.
+const BATCH_CONCURRENCY: usize = 4;
+
+/// Sends `toggle` for each of `ids`, a few at a time, and reports how they
+/// all went together once they're done.
+async fn send_batch(
+    toggle: Toggle,
+    ids: Vec<u64>,
+    on: bool,
+    token: egg_mode::Token,
+    retries: Retries,
+    events: mpsc::UnboundedSender<Event>,
+) {
+    let permits = std::sync::Arc::new(Semaphore::new(BATCH_CONCURRENCY));
+    let handles: Vec<_> = ids
+        .into_iter()
+        .map(|id| {
+            let permits = permits.clone();
+            let token = token.clone();
+            tokio::spawn(async move {
+                let _permit = permits.acquire().await;
+                let result =
+                    with_retry(retries, toggle.verb(on), || toggle.send(id, on, &token)).await;
+                (id, result.map(|r| Box::new(r.response)))
+            })
+        })
+        .collect();
+    let mut results = vec![];
+    for handle in handles {
+        if let Ok(result) = handle.await {
+            results.push(result);
+        }
+    }
+    let _ = events.send(Event::BatchToggled {
+        toggle,
+        on,
+        results,
+    });
+}
+
+/// Sends `tweets` one after another, each replying to the one before, and
+/// the first to `reply_to` if there is one. The first also quotes the tweet
+/// at `quote`, if there is one. Stops at the first that fails. `from` is the
+/// timeline it was composed on.
+async fn send_thread(
+    tweets: Vec<String>,
+    mut reply_to: Option<u64>,
+    mut quote: Option<String>,
+    from: TimelineKind,
+    token: egg_mode::Token,
+    retries: Retries,
+    events: mpsc::UnboundedSender<Event>,
+) {
+    let mut sent = vec![];
+    for (i, text) in tweets.iter().enumerate() {
+        let draft = DraftTweet::new(text.clone());
+        let draft = match reply_to {
+            Some(id) => draft.in_reply_to(id),
+            None => draft,
+        };
+        let draft = match quote.take() {
+            Some(url) => draft.attachment_url(url),
+            None => draft,
+        };
+        match with_retry(retries, "tweet", || draft.send(&token)).await {
+            Ok(response) => {
+                reply_to = Some(response.id);
+                sent.push(response.response);
+            }
+            Err(e) => {
+                let _ = events.send(Event::ThreadSent {
+                    from,
+                    sent,
+                    unsent: tweets[i..].to_vec(),
+                    result: Err(e),
+                });
+                return;
+            }
+        }
+    }
+    let _ = events.send(Event::ThreadSent {
+        from,
+        sent,
+        unsent: vec![],
+        result: Ok(()),
+    });
+}
+
+/// Fetches the first page of `handle`'s tweets before handing the timeline
+/// over, so that a missing or protected account can be reported up front
+/// rather than replacing the current timeline with an empty one.
+async fn load_user_timeline(
+    handle: String,
+    token: egg_mode::Token,
+    page_size: u32,
+    retries: Retries,
+    events: mpsc::UnboundedSender<Event>,
+) {
+    let mut timeline =
+        egg_mode::tweet::user_timeline(UserID::from(handle.clone()), true, true, &token)
+            .with_page_size(page_size as i32);
+
+    let result = with_retry(retries, "user_timeline", || timeline.call(None, None))
+        .await
+        .map(|response| {
+            track_cursor(&mut timeline, &response.response);
+            (timeline, response.response)
+        });
+
+    let _ = events.send(Event::UserTimeline(handle, result));
+}
+
+fn tweet_url(tweet: &Tweet) -> String {
+    // Twitter redirects this to the right place when we don't know the author.
+    let author = handle(tweet).unwrap_or("i/web");
+    format!("https://twitter.com/{}/status/{}", author, tweet.id)
+}
+
+/// The command that opens a URL in the default browser.
+#[cfg(target_os = "macos")]
+const OPEN_COMMAND: &[&str] = &["open"];
+#[cfg(target_os = "windows")]
+const OPEN_COMMAND: &[&str] = &["cmd", "/C", "start", ""];
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const OPEN_COMMAND: &[&str] = &["xdg-open"];
+
+/// Opens `url` in the default browser. Some openers don't return until the
+/// browser does, so this waits on a separate thread and reports back with
+/// an `Event::Opened`. The opener's output is thrown away so that it can't
+/// scribble over the UI.
+fn open_in_browser(url: String, events: mpsc::UnboundedSender<Event>) {
+    std::thread::spawn(move || {
+        let result = std::process::Command::new(OPEN_COMMAND[0])
+            .args(&OPEN_COMMAND[1..])
+            .arg(&url)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .and_then(|status| {
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(io::Error::other(format!(
+                        "{} failed with {}",
+                        OPEN_COMMAND[0], status
+                    )))
+                }
+            });
+        let _ = events.send(Event::Opened(result));
+    });
+}
+
+/// How many characters of a mention are shown in its notification.
+const NOTIFICATION_LENGTH: usize = 100;
+
+/// Shows a notification for each mention in `results` newer than any seen
+/// before. Whatever the first fetch finds is taken as already seen, so that
+/// starting up doesn't bring up a flurry of old ones.
+async fn notify_mentions(mut results: mpsc::UnboundedReceiver<Fetched>) {
+    let mut seen = None;
+    let mut started = false;
+
+    while let Some(fetched) = results.recv().await {
+        let mentions = match fetched.tweets {
+            Ok(mentions) => mentions,
+            Err(_) => continue,
+        };
+        if started {
+            for mention in mentions.iter().filter(|mention| Some(mention.id) > seen) {
+                // A retweet's own text is cut short after an "RT @handle:",
+                // but the original it carries is complete.
+                let original = mention.retweeted_status.as_deref().unwrap_or(mention);
+                let full = LinkedText::new(original).text;
+                let mut text: String = full.chars().take(NOTIFICATION_LENGTH).collect();
+                if full.chars().count() > NOTIFICATION_LENGTH {
+                    text.push('…');
+                }
+                notify(
+                    "new mention",
+                    &format!("@{}: {}", screen_name(mention), text),
+                );
+            }
+        }
+        seen = seen.max(mentions.iter().map(|mention| mention.id).max());
+        started = true;
+    }
+}
+
+/// Shows a desktop notification on a separate thread, for the same reasons
+/// as `open_in_browser`. Notifications are a nicety, so if one can't be
+/// shown it's only logged.
+fn notify(title: &str, body: &str) {
+    let mut notification = notify_rust::Notification::new();
+    notification.summary(title).body(body);
+    std::thread::spawn(move || {
+        if let Err(e) = notification.show() {
+            tracing::warn!("couldn't show notification: {}", e);
+        }
+    });
+}
+
+/// What to show in place of a tweet that fetching failed with `e`, if `e`
+/// means it's been deleted or belongs to an account we can't see, rather than
+/// that something went wrong fetching it.
+fn unavailable_placeholder(e: &egg_mode::error::Error) -> Option<&'static str> {
+    let status = match e {
+        egg_mode::error::Error::BadStatus(status) => status.as_u16(),
+        _ => 0,
+    };
+    // Twitter usually says why with an error code, and only falls back on
+    // the bare 403 or 404 when it doesn't.
+    if has_error_code(e, &[179]) || status == 403 {
+        Some("🔒 this tweet is from a protected account")
+    } else if has_error_code(e, &[144]) || status == 404 {
+        Some("🗑 this tweet was deleted")
+    } else if has_error_code(e, &[63]) {
+        Some("this tweet is from a suspended account")
+    } else {
+        None
+    }
+}
+
+/// Whether `e` means our credentials are no good, so there's no point trying
+/// again. Anything else, like a dropped connection or Twitter having a bad
+/// moment, might go away by itself.
+fn is_fatal(e: &egg_mode::error::Error) -> bool {
+    // Bad authentication data, suspended account, or locked account.
+    is_revoked(e) || has_error_code(e, &[215, 64, 326])
+}
+
+/// Turns the errors Twitter gives for accounts we can't read into something
+/// readable.
+fn describe_user_error(handle: &str, e: &egg_mode::error::Error) -> String {
+    use egg_mode::error::Error::{BadStatus, TwitterError};
+
+    match e {
+        // 34: page does not exist, 50: user not found.
+        TwitterError(_, errors) if errors.errors.iter().any(|e| e.code == 34 || e.code == 50) => {
+            format!("@{} doesn't exist", handle)
+        }
+        BadStatus(status) if status.as_u16() == 401 => {
+            format!("@{}'s tweets are protected", handle)
+        }
+        e => format!("couldn't load @{}: {}", handle, e),
+    }
+}
+
+/// While the UI is up, the guard that puts the terminal back in cooked mode,
+/// shared so the panic hook can use it.
+static RAW_MODE: Mutex<Option<Arc<RawTerminal<io::Stdout>>>> = Mutex::new(None);
+
+/// Turns off the mouse reporting `MouseTerminal` turns on.
+const EXIT_MOUSE: &str = "\x1b[?1006l\x1b[?1015l\x1b[?1002l\x1b[?1000l";
+
+/// The default panic hook prints while we're still on the alternate screen,
+/// so the message is lost when it's torn down and the cursor is left hidden.
+/// Put the terminal back the way we found it first so the panic is readable.
+///
+/// A panic in a spawned task would otherwise leave the UI running on a
+/// terminal we've just given back, so exit once it's been reported.
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let raw = RAW_MODE.lock().ok().and_then(|mut raw| raw.take());
+        if let Some(raw) = &raw {
+            let mut stdout = io::stdout();
+            let _ = write!(stdout, "{}{}{}", EXIT_MOUSE, ToMainScreen, cursor::Show);
+            let _ = stdout.flush();
+            let _ = raw.suspend_raw_mode();
+        }
+        default_hook(info);
+        if raw.is_some() {
+            std::process::exit(101);
+        }
+    }));
+}
+
+const USAGE: &str = "\
+usage: twrs [options]
+
+options:
+  -c, --config <path>  read the config from <path> instead of the default
+      --mock <path>    show the tweets in the JSON file at <path> instead of
+                       fetching any, which can also be set with TWRS_MOCK
+  -h, --help           show this message
+  -V, --version        show the version
+
+warnings and errors are logged to twrs/twrs.log in your cache directory. set
+RUST_LOG, or level in the [log] table of the config, to log more or less, like
+RUST_LOG=twrs=debug.
+";
+
+/// What the command line asked for.
+enum Command {
+    Run {
+        config: Option<PathBuf>,
+        /// A fixture to show instead of talking to Twitter.
+        mock: Option<PathBuf>,
+    },
+    Help,
+    Version,
+}
+
+fn parse_args<I: IntoIterator<Item = OsString>>(args: I) -> Result<Command> {
+    let mut args = args.into_iter();
+    let mut config = None;
+    let mut mock = None;
+
+    while let Some(arg) = args.next() {
+        let arg = arg
+            .into_string()
+            .map_err(|arg| Error::Usage(format!("unknown argument {:?}", arg)))?;
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "-c" | "--config" => match args.next() {
+                Some(path) => config = Some(PathBuf::from(path)),
+                None => return Err(Error::Usage(format!("{} needs a path", arg))),
+            },
+            "--mock" => match args.next() {
+                Some(path) => mock = Some(PathBuf::from(path)),
+                None => return Err(Error::Usage(format!("{} needs a path", arg))),
+            },
+            _ => {
+                if let Some(path) = arg.strip_prefix("--config=") {
+                    config = Some(PathBuf::from(path));
+                } else if let Some(path) = arg.strip_prefix("--mock=") {
+                    mock = Some(PathBuf::from(path));
+                } else {
+                    return Err(Error::Usage(format!("unknown argument {:?}", arg)));
+                }
+            }
+        }
+    }
+
+    Ok(Command::Run { config, mock })
+}
+
+/// Makes a config with one account for the first run, asking for the details
+/// of the Twitter app to use. Authorizing the account is left to
+/// `authenticate`.
+fn set_up(config_path: &Path) -> Result<Config> {
+    println!(
+        "there's no config at {}, so let's make one",
+        config_path.display()
+    );
+    println!("twrs needs the API key and secret of a Twitter app to talk to Twitter.");
+    println!("you can create an app, and find its key and secret, at");
+    println!("https://developer.twitter.com/en/portal/projects-and-apps");
+
+    let key: String = Input::new().with_prompt("API key").interact_text()?;
+    let secret = Password::new().with_prompt("API secret").interact()?;
+
+    let config = Config {
+        twitter: None,
+        ui: Ui::default(),
+        theme: Theme::default(),
+        filters: Filters::default(),
+        notifications: Notifications::default(),
+        confirm: Confirmations::default(),
+        log: Log::default(),
+        network: Network::default(),
+        keys: BTreeMap::new(),
+        keymap: Keymap::default(),
+        fixture: None,
+        accounts: vec![Twitter {
+            label: None,
+            key,
+            secret,
+            token: None,
+            bearer: None,
+        }],
+    };
+    if let Some(dir) = config_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(config_path, toml::to_string_pretty(&config)?)?;
+
+    Ok(config)
+}
+
+/// Asks which account to use if there's more than one to choose from.
+fn choose_account(config: &Config) -> Result<usize> {
+    if config.accounts.len() == 1 {
+        return Ok(0);
+    }
+
+    let labels: Vec<String> = config
+        .accounts
+        .iter()
+        .enumerate()
+        .map(|(i, account)| account.label(i))
+        .collect();
+
+    Ok(Select::new()
+        .with_prompt("account")
+        .items(&labels)
+        .default(0)
+        .interact()?)
+}
+
+/// What `action` does, for saying it can't be done, if it has to be done as
+/// a user rather than with a bearer token.
+fn needs_user(action: Action) -> Option<&'static str> {
+    Some(match action {
+        Action::Home => "the home timeline",
+        Action::Mentions => "mentions",
+        Action::Compose => "tweeting",
+        Action::Reply => "replying",
+        Action::Quote => "quote tweeting",
+        Action::Like => "liking",
+        Action::Retweet => "retweeting",
+        Action::Undo => "undoing",
+        Action::Follow => "following",
+        Action::Delete => "deleting",
+        Action::Messages => "direct messages",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use tui::backend::TestBackend;
+
+    /// A user called `screen_name`, as Twitter would send them.
+    pub(crate) fn user(screen_name: &str) -> Value {
+        json!({
+            "contributors_enabled": false,
+            "created_at": "Wed Oct 10 20:19:24 +0000 2018",
+            "default_profile": true,
+            "default_profile_image": true,
+            "entities": { "description": { "urls": [] } },
+            "favourites_count": 0,
+            "followers_count": 0,
+            "friends_count": 0,
+            "geo_enabled": false,
+            "id": 1,
+            "is_translator": false,
+            "listed_count": 0,
+            "name": screen_name,
+            "profile_background_color": "",
+            "profile_image_url": "",
+            "profile_image_url_https": "",
+            "profile_link_color": "",
+            "profile_sidebar_border_color": "",
+            "profile_sidebar_fill_color": "",
+            "profile_text_color": "",
+            "profile_use_background_image": false,
+            "protected": false,
+            "screen_name": screen_name,
+            "statuses_count": 0,
+            "verified": false,
+        })
+    }
+
+    /// A tweet as Twitter would send it, with `fields` set over the least
+    /// egg-mode will read.
+    fn tweet(fields: Value) -> Tweet {
+        serde_json::from_value(tweet_json(fields)).unwrap()
+    }
+
+    /// The JSON for `tweet`, for tweets that other tweets include.
+    pub(crate) fn tweet_json(fields: Value) -> Value {
+        let mut tweet = json!({
+            "created_at": "Wed Oct 10 20:19:24 +0000 2018",
+            "entities": { "hashtags": [], "symbols": [], "urls": [], "user_mentions": [] },
+            "favorite_count": 0,
+            "id": 1,
+            "retweet_count": 0,
+            "source": "<a href=\"https://twitter.com\" rel=\"nofollow\">Twitter Web App</a>",
+            "full_text": "",
+            "truncated": false,
+            "user": user("someone"),
+        });
+        for (key, value) in fields.as_object().unwrap() {
+            tweet[key] = value.clone();
+        }
+        tweet
+    }
+
+    fn styles() -> Rc<Styles> {
+        Rc::new(Styles::from_config(&Config::default()).unwrap())
+    }
+
+    fn timeline(tweets: Vec<Tweet>) -> TimelineRenderer {
+        timeline_of(TimelineKind::Home, tweets)
+    }
+
+    fn timeline_of(kind: TimelineKind, tweets: Vec<Tweet>) -> TimelineRenderer {
+        let mutes = Rc::new(Mutes::new(&Filters::default()).unwrap());
+        let mut timeline = TimelineRenderer::offline(kind, &Ui::default(), styles(), mutes);
+        timeline.insert(tweets);
+        timeline
+    }
+
+    /// Everything drawn into `buf`, a line of text for each row.
+    fn text(buf: &Buffer) -> String {
+        let area = buf.area();
+        let mut text = String::new();
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                text.push_str(&buf.get(x, y).symbol);
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    fn render(timeline: &mut TimelineRenderer, width: u16, height: u16) -> String {
+        let mut buf = Buffer::empty(Rect::new(0, 0, width, height));
+        timeline.render(buf.area, &mut buf);
+        text(&buf)
+    }
+
+    /// Draws into a buffer rather than a terminal, and throws away anything
+    /// written straight to it, like thumbnails.
+    struct Headless(TestBackend);
+
+    impl Backend for Headless {
+        fn draw<'a, I>(&mut self, content: I) -> io::Result<()>
+        where
+            I: Iterator<Item = (u16, u16, &'a tui::buffer::Cell)>,
+        {
+            self.0.draw(content)
+        }
+
+        fn hide_cursor(&mut self) -> io::Result<()> {
+            self.0.hide_cursor()
+        }
+
+        fn show_cursor(&mut self) -> io::Result<()> {
+            self.0.show_cursor()
+        }
+
+        fn get_cursor(&mut self) -> io::Result<(u16, u16)> {
+            self.0.get_cursor()
+        }
+
+        fn set_cursor(&mut self, x: u16, y: u16) -> io::Result<()> {
+            self.0.set_cursor(x, y)
+        }
+
+        fn clear(&mut self) -> io::Result<()> {
+            self.0.clear()
+        }
+
+        fn size(&self) -> io::Result<Rect> {
+            self.0.size()
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Backend::flush(&mut self.0)
+        }
+    }
+
+    impl Write for Headless {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn terminal() -> Terminal<Headless> {
+        Terminal::new(Headless(TestBackend::new(80, 24))).unwrap()
+    }
+
+    /// An app playing back an empty fixture, so it never talks to Twitter.
+    fn app(events: mpsc::UnboundedSender<Event>) -> App {
+        let config = Config {
+            fixture: Some(vec![]),
+            ..Config::default()
+        };
+        let styles = Styles::from_config(&config).unwrap();
+        let mutes = Mutes::new(&config.filters).unwrap();
+        let token = egg_mode::Token::Access {
+            consumer: KeyPair::new("", ""),
+            access: KeyPair::new("", ""),
+        };
+        App::new(config, PathBuf::new(), styles, mutes, 0, token, events)
+    }
+
+    /// A timeline Twitter answers with the error `code` every time, like 89
+    /// for a token it no longer accepts.
+    struct Failing(i32);
+
+    impl Failing {
+        fn error(&self) -> egg_mode::error::Error {
+            let errors = json!({ "errors": [{ "message": "no", "code": self.0 }] });
+            egg_mode::error::Error::TwitterError(
+                Default::default(),
+                serde_json::from_value(errors).unwrap(),
+            )
+        }
+    }
+
+    impl TimelineSource for Failing {
+        fn newer(&mut self) -> Page<'_> {
+            Box::pin(std::future::ready(Err(self.error())))
+        }
+
+        fn older(&mut self, _oldest: u64) -> Page<'_> {
+            Box::pin(std::future::ready(Err(self.error())))
+        }
+    }
+
+    #[tokio::test]
+    async fn a_revoked_token_ends_the_run_to_authorize_again() {
+        let (events_tx, events) = mpsc::unbounded_channel();
+        let mut app = app(events_tx);
+        let (styles, mutes) = (app.styles.clone(), app.mutes.clone());
+        let source = Box::new(Failing(89));
+        app.timeline =
+            TimelineRenderer::new(TimelineKind::Home, source, &Ui::default(), styles, mutes);
+        let (_keys, input) = std::sync::mpsc::channel();
+
+        let mut terminal = terminal();
+        let run = run(&mut terminal, app, events, &input);
+        let result = tokio::time::timeout(Duration::from_secs(5), run).await;
+        assert!(matches!(result, Ok(Err(Error::Revoked(0)))));
+    }
+
+    #[tokio::test]
+    async fn a_background_tab_that_stops_fetching_says_so_and_the_app_carries_on() {
+        let (events_tx, events) = mpsc::unbounded_channel();
+        let mut app = app(events_tx);
+        let (styles, mutes) = (app.styles.clone(), app.mutes.clone());
+        // Twitter's code for a suspended account.
+        let source = Box::new(Failing(64));
+        let suspended = TimelineRenderer::new(
+            TimelineKind::Mentions,
+            source,
+            &Ui::default(),
+            styles,
+            mutes,
+        );
+        app.tabs = vec![None, Some(suspended)];
+        let (keys, input) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(500));
+            let _ = keys.send(Event::Key(Key::Char('q')));
+        });
+
+        let mut terminal = terminal();
+        let run = run(&mut terminal, app, events, &input);
+        let result = tokio::time::timeout(Duration::from_secs(5), run).await;
+        assert!(matches!(result, Ok(Ok(()))));
+        let drawn = text(terminal.backend().0.buffer());
+        assert!(drawn.contains("mentions !"), "{}", drawn);
+    }
+
+    #[tokio::test]
+    async fn pressing_q_quits() {
+        let (events_tx, events) = mpsc::unbounded_channel();
+        let app = app(events_tx);
+        let (keys, input) = std::sync::mpsc::channel();
+        keys.send(Event::Key(Key::Char('q'))).unwrap();
+
+        let mut terminal = terminal();
+        let run = run(&mut terminal, app, events, &input);
+        let result = tokio::time::timeout(Duration::from_secs(5), run).await;
+        assert!(matches!(result, Ok(Ok(()))));
+    }
+
+    #[test]
+    fn resizing_keeps_the_same_tweet_selected_and_in_view() {
+        let tweets = (1..=20).map(|id| {
+            let text = format!(
+                "tweet-{} is long enough to wrap once the terminal narrows",
+                id
+            );
+            tweet(json!({ "id": id, "full_text": text }))
+        });
+        let mut timeline = timeline(tweets.collect());
+        timeline.state.select(Some(10));
+        render(&mut timeline, 120, 10);
+
+        timeline.resized();
+        let drawn = render(&mut timeline, 30, 10);
+        assert_eq!(timeline.selected().map(|tweet| tweet.id), Some(10));
+        assert!(drawn.contains("tweet-10"), "{}", drawn);
+    }
+
+    #[test]
+    fn selecting_past_the_end_stays_on_the_last_tweet_and_fetches_older_ones() {
+        let (requests, mut requests_rx) = mpsc::unbounded_channel();
+        let (_, results) = mpsc::unbounded_channel();
+        let (_, busy) = watch::channel(false);
+        let mutes = Rc::new(Mutes::new(&Filters::default()).unwrap());
+        let ui = Ui::default();
+        let mut timeline = TimelineRenderer::with_channels(
+            TimelineKind::Home,
+            requests,
+            results,
+            busy,
+            &ui,
+            styles(),
+            mutes,
+        );
+        timeline.insert((1..=3).map(|id| tweet(json!({ "id": id }))).collect());
+
+        for _ in 0..5 {
+            timeline.select_next();
+        }
+        assert_eq!(timeline.state.selected(), Some(2));
+        assert!(timeline.fetching_older && !timeline.prefetching);
+        let request = requests_rx.try_recv();
+        assert!(matches!(request, Ok(Request::Fetch(Fetch::Older(1)))));
+    }
+
+    /// The text of each of `lines`.
+    fn line_text(lines: &Lines) -> Vec<String> {
+        let text = lines.spans.iter().map(|line| {
+            let spans = line.0.iter().map(|span| span.content.as_ref());
+            spans.collect::<String>()
+        });
+        text.collect()
+    }
+
+    /// The column `text` starts at in `line`, if it's there.
+    fn column(line: &str, text: &str) -> Option<usize> {
+        line.find(text).map(|i| line[..i].width())
+    }
+
+    #[test]
+    fn retweets_credit_the_retweeter_above_the_original_and_its_quote() {
+        let quoted = tweet_json(json!({ "id": 1, "full_text": "quoted", "user": user("quoter") }));
+        let original = tweet_json(json!({
+            "id": 2,
+            "full_text": "original",
+            "user": user("author"),
+            "quoted_status": quoted,
+        }));
+        let retweet = tweet(json!({
+            "id": 3,
+            "user": user("retweeter"),
+            "retweeted_status": original,
+        }));
+        let lines = tweet_lines(&retweet, 0, "1m".to_string(), false, false, 60, &styles());
+        let text = line_text(&lines);
+        assert_eq!(text.len(), 3, "{:#?}", text);
+        assert!(
+            text[0].ends_with("1m retweeter 🔁 retweeted"),
+            "{:#?}",
+            text
+        );
+        // The original's lined up after the timestamp, and the quote under that.
+        let indent = column(&text[0], "retweeter");
+        assert_eq!(column(&text[1], "author original"), indent, "{:#?}", text);
+        assert_eq!(column(&text[2], "│ quoter quoted"), indent, "{:#?}", text);
+    }
+
+    #[test]
+    fn retweets_line_up_when_emoji_are_a_column_wide() {
+        let mut config = Config::default();
+        config.ui.emoji_width = Some(1);
+        let styles = Styles::from_config(&config).unwrap();
+        let original =
+            tweet_json(json!({ "id": 1, "full_text": "original", "user": user("author") }));
+        let retweet = tweet(json!({
+            "id": 2,
+            "user": user("retweeter"),
+            "retweeted_status": original,
+        }));
+        let lines = tweet_lines(&retweet, 0, "1m".to_string(), false, false, 60, &styles);
+        let text = line_text(&lines);
+        // The 🔁 glyph takes up a column here, not the two unicode-width says.
+        let column = |line: &str, text: &str| {
+            line.find(text)
+                .map(|i| text_width(&line[..i], styles.emoji_width))
+        };
+        assert_eq!(
+            column(&text[1], "author original"),
+            column(&text[0], "retweeter"),
+            "{:#?}",
+            text
+        );
+    }
+
+    #[test]
+    fn timestamps_count_up_in_whole_units_until_a_week_has_passed() {
+        let mut styles = Styles::from_config(&Config::default()).unwrap();
+        styles.zone = Zone::Fixed(chrono::FixedOffset::east(0));
+        let now = Utc.ymd(2020, 3, 15).and_hms(12, 0, 0);
+        let ago = |age: chrono::Duration| format_timestamp(now - age, now, false, &styles);
+
+        assert_eq!(ago(chrono::Duration::seconds(9)), "just now");
+        assert_eq!(ago(chrono::Duration::seconds(59)), "59s");
+        assert_eq!(ago(chrono::Duration::seconds(60)), "1m");
+        assert_eq!(ago(chrono::Duration::minutes(59)), "59m");
+        assert_eq!(ago(chrono::Duration::minutes(60)), "1h");
+        assert_eq!(ago(chrono::Duration::hours(23)), "23h");
+        assert_eq!(ago(chrono::Duration::hours(24)), "1d");
+        assert_eq!(ago(chrono::Duration::days(6)), "6d");
+        assert_eq!(ago(chrono::Duration::days(7)), "Mar 08");
+    }
+
+    #[test]
+    fn only_the_newest_tweets_are_kept_but_never_the_selected_one() {
+        let mut timeline = timeline(vec![]);
+        timeline.max_tweets = 20;
+        for id in 1..=30 {
+            timeline.insert(vec![tweet(json!({ "id": id }))]);
+        }
+        assert_eq!(timeline.tweets.len(), 20);
+        let ids: Vec<u64> = timeline.listed().map(|tweet| tweet.id).collect();
+        assert_eq!(ids, (11..=30).rev().collect::<Vec<u64>>());
+
+        // Scrolled down to the oldest, newer tweets coming in don't push it out.
+        timeline.select_oldest();
+        for id in 31..=40 {
+            timeline.insert(vec![tweet(json!({ "id": id }))]);
+        }
+        assert_eq!(timeline.selected().map(|tweet| tweet.id), Some(11));
+
+        // Muted tweets aren't on screen, so they don't count towards what's
+        // kept below the selection.
+        let filters = Filters {
+            keywords: vec!["muted".to_string()],
+            ..Filters::default()
+        };
+        let mut timeline = timeline_of(TimelineKind::Home, vec![]);
+        timeline.set_mutes(Rc::new(Mutes::new(&filters).unwrap()));
+        timeline.height = 3;
+        // Every other tweet is muted, so 15 of the 30 are listed.
+        let tweets = (1..=30).map(|id| {
+            let text = if id % 2 == 0 { "muted" } else { "shown" };
+            tweet(json!({ "id": id, "full_text": text }))
+        });
+        timeline.insert(tweets.collect());
+        timeline.max_tweets = 20;
+        let listed = timeline.listed().count();
+        timeline.state.select(Some(listed - 1));
+        let selected = timeline.selected().map(|tweet| tweet.id);
+
+        timeline.insert(vec![tweet(json!({ "id": 31, "full_text": "shown" }))]);
+        assert_eq!(timeline.selected().map(|tweet| tweet.id), selected);
+        assert_eq!(timeline.listed().last().map(|tweet| tweet.id), Some(1));
+
+        // Scrolled back to the top, the old ones can go.
+        timeline.select_newest();
+        timeline.insert(vec![tweet(json!({ "id": 32, "full_text": "shown" }))]);
+        assert_eq!(timeline.tweets.len(), 20);
+        let ids: Vec<u64> = timeline.listed().take(4).map(|tweet| tweet.id).collect();
+        assert_eq!(ids, [32, 31, 29, 27]);
+    }
+
+    #[test]
+    fn mutes_match_keywords_patterns_and_authors_whatever_their_case() {
+        let filters = Filters {
+            keywords: vec!["Spoiler".to_string(), "/gr[ae]y/".to_string()],
+            users: vec!["@Troll".to_string()],
+            ..Filters::default()
+        };
+        let mutes = Mutes::new(&filters).unwrap();
+        let hides = |fields| mutes.hides(&tweet(fields));
+
+        assert!(hides(json!({ "full_text": "major SPOILER ahead" })));
+        assert!(hides(json!({ "full_text": "so grey today" })));
+        assert!(hides(json!({ "full_text": "SO GRAY TODAY" })));
+        assert!(hides(
+            json!({ "full_text": "hello", "user": user("TROLL") })
+        ));
+        let retweeted = tweet_json(json!({ "full_text": "hello" }));
+        assert!(hides(
+            json!({ "user": user("troll"), "retweeted_status": retweeted })
+        ));
+
+        assert!(!hides(json!({ "full_text": "nothing to see here" })));
+        assert!(!hides(
+            json!({ "full_text": "hello", "user": user("trolley") })
+        ));
+    }
+
+    #[test]
+    fn html_entities_are_drawn_as_the_characters_they_stand_for() {
+        let text = "Tom &amp; Jerry &lt;3 &gt;_&lt; &amp;lt; &copy;";
+        let mut timeline = timeline(vec![tweet(json!({ "full_text": text }))]);
+        let drawn = render(&mut timeline, 80, 5);
+        assert!(
+            drawn.contains("Tom & Jerry <3 >_< &lt; &copy;"),
+            "{}",
+            drawn
+        );
+    }
+
+    #[test]
+    fn the_source_is_the_text_of_its_anchor_if_it_has_one() {
+        let via = |source: &str| {
+            let tweet = tweet(json!({ "full_text": "hi", "source": source }));
+            let lines = status_lines(&tweet, vec![], 0, true, true, 80, &styles());
+            let text = line_text(&lines).concat();
+            text.find("via ").map(|i| text[i..].to_string())
+        };
+
+        let anchor =
+            r#"<a href="http://twitter.com/download/iphone" rel="nofollow">Twitter for iPhone</a>"#;
+        assert_eq!(via(anchor).as_deref(), Some("via Twitter for iPhone"));
+        assert_eq!(via("web").as_deref(), Some("via Twitter Web Client"));
+        // Anything that isn't a whole anchor is left out rather than shown raw.
+        assert_eq!(via(r#"<a href="https://example.com">Unclosed"#), None);
+        assert_eq!(via(r#"<a rel="nofollow">No link</a>"#), None);
+        assert_eq!(via("Some app"), None);
+    }
+
+    #[test]
+    fn tweets_without_an_author_are_drawn_as_by_someone_unknown() {
+        let anonymous = json!({ "id": 1, "full_text": "who wrote this?", "user": null });
+        let retweet =
+            json!({ "id": 2, "user": null, "retweeted_status": tweet_json(anonymous.clone()) });
+        let mut timeline = timeline(vec![tweet(anonymous), tweet(retweet)]);
+        timeline.state.select(Some(0));
+        let drawn = render(&mut timeline, 120, 20);
+        assert!(drawn.contains("[unknown] who wrote this?"), "{}", drawn);
+        assert!(drawn.contains("[unknown] 🔁"), "{}", drawn);
+    }
+
+    #[test]
+    fn counts_are_rounded_to_a_tenth_of_the_largest_unit_that_fits() {
+        assert_eq!(humanize_count(999), "999");
+        assert_eq!(humanize_count(1_000), "1K");
+        assert_eq!(humanize_count(1_234), "1.2K");
+        assert_eq!(humanize_count(999_950), "1M");
+        assert_eq!(humanize_count(2_500_000), "2.5M");
+        assert_eq!(humanize_count(1_000_000_000), "1B");
+    }
+
+    #[test]
+    fn replies_to_someone_else_say_who_they_reply_to() {
+        let reply = |to: u64| {
+            let reply = tweet(json!({
+                "full_text": "same",
+                "in_reply_to_status_id": 10,
+                "in_reply_to_user_id": to,
+                "in_reply_to_screen_name": "alice",
+            }));
+            replying_to_line(&reply, vec![Span::raw("  ")], &styles())
+        };
+
+        let lines = reply(2);
+        assert_eq!(line_text(&lines), ["  replying to @alice"]);
+        let (line, span, link) = &lines.links[0];
+        assert_eq!((*line, *span), (0, 2));
+        assert!(matches!(link, Link::Mention(name) if name == "alice"));
+        assert_eq!(lines.spans[0].0[2].style, styles().link);
+
+        // Threads of the author's own, and tweets that aren't replies, don't.
+        assert!(reply(1).spans.is_empty());
+        let tweet = tweet(json!({ "full_text": "same" }));
+        assert!(replying_to_line(&tweet, vec![], &styles()).spans.is_empty());
+    }
+
+    #[test]
+    fn each_kind_of_tweet_gets_its_glyph_padded_to_the_widest() {
+        let glyphs = &styles().glyphs;
+        let reply = json!({ "in_reply_to_status_id": 10 });
+        let quote = json!({ "quoted_status_id": 10 });
+        let retweet = json!({ "retweeted_status": tweet_json(reply.clone()) });
+
+        assert_eq!(glyphs.column(&tweet(reply)), "↩  ");
+        assert_eq!(glyphs.column(&tweet(retweet)), "🔁 ");
+        assert_eq!(glyphs.column(&tweet(quote)), "❝  ");
+        assert_eq!(glyphs.column(&tweet(json!({}))), "   ");
+
+        // With no glyphs at all, there's no column for them.
+        let none = Glyphs::new("", "", "", None);
+        assert_eq!(none.column(&tweet(json!({ "quoted_status_id": 10 }))), "");
+    }
+
+    #[test]
+    fn only_listed_languages_are_shown_but_unknown_ones_always_are() {
+        let filters = Filters {
+            languages: vec!["EN".to_string(), "fr".to_string()],
+            ..Filters::default()
+        };
+        let mutes = Mutes::new(&filters).unwrap();
+        let hides = |fields| mutes.hides(&tweet(fields));
+
+        assert!(!hides(json!({ "lang": "en" })));
+        assert!(!hides(json!({ "lang": "FR" })));
+        assert!(hides(json!({ "lang": "de" })));
+        // A retweet's in whatever language the original is.
+        let original = tweet_json(json!({ "lang": "de" }));
+        assert!(hides(json!({ "lang": "en", "retweeted_status": original })));
+        assert!(!hides(json!({ "lang": "und" })));
+        assert!(!hides(json!({})));
+
+        // Nothing listed means nothing's hidden for its language.
+        let mutes = Mutes::new(&Filters::default()).unwrap();
+        assert!(!mutes.hides(&tweet(json!({ "lang": "de" }))));
+    }
+
+    #[test]
+    fn emoji_take_up_as_many_columns_as_configured() {
+        let samples = [
+            ("a", 1),
+            ("⚡", 2),
+            ("👍🏽", 2),
+            ("👩‍💻", 2),
+            ("🇬🇧", 2),
+            ("♥️", 2),
+            // Symbols only drawn as emoji when asked to be.
+            ("♥", 1),
+            ("✓", 1),
+            ("❝", 1),
+        ];
+        for (text, width) in samples.iter() {
+            assert_eq!(text_width(text, Some(2)), *width, "{:?}", text);
+            assert_eq!(text_width(text, Some(1)), 1, "{:?}", text);
+        }
+        assert_eq!(text_width("hi 👩‍💻 ✓", Some(2)), 7);
+        assert_eq!(text_width("hi 👩‍💻 ✓", Some(1)), 6);
+
+        // The glyph column is measured the same way.
+        assert_eq!(Glyphs::new("↩", "🔁", "❝", Some(1)).width, 2);
+        assert_eq!(Glyphs::new("↩", "🔁", "❝", Some(2)).width, 3);
+    }
+
+    #[tokio::test]
+    async fn toggles_and_sent_tweets_reach_every_open_timeline() {
+        let (events_tx, _events) = mpsc::unbounded_channel();
+        let mut app = app(events_tx);
+        app.timeline = timeline_of(TimelineKind::Home, vec![tweet(json!({ "id": 1 }))]);
+        let mentions = timeline_of(TimelineKind::Mentions, vec![tweet(json!({ "id": 1 }))]);
+        app.tabs = vec![None, Some(mentions)];
+        let ids = |timeline: &TimelineRenderer| -> Vec<u64> {
+            timeline.listed().map(|tweet| tweet.id).collect()
+        };
+
+        app.handle_event(Event::Toggled {
+            toggle: Toggle::Like,
+            id: 1,
+            on: true,
+            result: Ok(Box::new(tweet(json!({ "id": 1, "favorite_count": 5 })))),
+        })
+        .unwrap();
+        for timeline in app.timelines() {
+            let liked = timeline.listed().next().unwrap();
+            assert_eq!((liked.favorited, liked.favorite_count), (Some(true), 5));
+        }
+
+        // A reply sent from the mentions tab lands there and on home, even
+        // though home is what's showing by the time it's sent.
+        let reply = tweet(json!({ "id": 2, "in_reply_to_status_id": 1 }));
+        app.handle_event(Event::Sent {
+            from: TimelineKind::Mentions,
+            result: Ok(Box::new(reply)),
+        })
+        .unwrap();
+        let plain = tweet(json!({ "id": 3 }));
+        app.handle_event(Event::Sent {
+            from: TimelineKind::Mentions,
+            result: Ok(Box::new(plain)),
+        })
+        .unwrap();
+        assert_eq!(ids(&app.timeline), [3, 2, 1]);
+        assert_eq!(ids(app.tabs[1].as_ref().unwrap()), [2, 1]);
+    }
+
+    #[tokio::test]
+    async fn messages_scroll_and_close_with_the_configured_keys() {
+        let (events_tx, _events) = mpsc::unbounded_channel();
+        let mut app = app(events_tx);
+        let keys = [(
+            "scroll_down".to_string(),
+            twrs::keys::Binding::One("w".to_string()),
+        )];
+        app.config.keymap = Keymap::new(&keys.iter().cloned().collect()).unwrap();
+        let hints = action_hints(&app.config.keymap, MESSAGES_HINTS);
+        assert_eq!(
+            hints,
+            [("w/k".to_string(), "scroll"), ("esc".to_string(), "close")]
+        );
+
+        app.mode = Mode::Messages(Messages {
+            lines: vec![Spans::default(); 3],
+            scroll: 0,
+        });
+        let scroll = |app: &App| match &app.mode {
+            Mode::Messages(messages) => Some(messages.scroll),
+            _ => None,
+        };
+        app.handle_messages_key(Key::Char('w'));
+        app.handle_messages_key(Key::Char('j'));
+        assert_eq!(scroll(&app), Some(1));
+        app.handle_messages_key(Key::Up);
+        app.handle_messages_key(Key::Char('g'));
+        assert_eq!(scroll(&app), Some(0));
+        app.handle_messages_key(Key::Esc);
+        assert_eq!(scroll(&app), None);
+    }
+
+    #[test]
+    fn the_header_says_which_keys_jump_to_the_top_and_clear_marks() {
+        let mut timeline = timeline(vec![tweet(json!({ "id": 1 })), tweet(json!({ "id": 2 }))]);
+        timeline.unseen = 2;
+        timeline.marked.insert(1);
+        let drawn = render(&mut timeline, 120, 10);
+        assert!(
+            drawn.contains("2 new tweets, press home to jump"),
+            "{}",
+            drawn
+        );
+        assert!(drawn.contains("1 tweet marked, esc to clear"), "{}", drawn);
+
+        let keys = [
+            ("top".to_string(), twrs::keys::Binding::One("w".to_string())),
+            ("back".to_string(), twrs::keys::Binding::Many(vec![])),
+        ];
+        let config = Config {
+            keymap: Keymap::new(&keys.iter().cloned().collect()).unwrap(),
+            ..Config::default()
+        };
+        timeline.styles = Rc::new(Styles::from_config(&config).unwrap());
+        let drawn = render(&mut timeline, 120, 10);
+        assert!(drawn.contains("2 new tweets, press w to jump"), "{}", drawn);
+        assert!(drawn.contains("1 tweet marked "), "{}", drawn);
+    }
+
+    #[test]
+    fn what_is_hidden_is_worked_out_again_only_when_something_changes() {
+        let mut timeline = timeline(vec![]);
+        timeline.collapse_retweets = true;
+        let original = tweet_json(json!({ "id": 1, "user": user("author") }));
+        timeline.insert(vec![
+            tweet(json!({ "id": 2, "user": user("first"), "retweeted_status": original })),
+            tweet(json!({ "id": 3, "user": user("second"), "retweeted_status": original })),
+        ]);
+        let ids = |timeline: &TimelineRenderer| -> Vec<u64> {
+            timeline.listed().map(|tweet| tweet.id).collect()
+        };
+        assert_eq!(ids(&timeline), [3]);
+        assert!(Rc::ptr_eq(&timeline.hidden(), &timeline.hidden()));
+
+        // Muting the newer retweeter leaves the older retweet standing in.
+        let filters = Filters {
+            users: vec!["second".to_string()],
+            ..Filters::default()
+        };
+        timeline.set_mutes(Rc::new(Mutes::new(&filters).unwrap()));
+        assert_eq!(ids(&timeline), [2]);
+
+        timeline.set_mutes(Rc::new(Mutes::new(&Filters::default()).unwrap()));
+        timeline.remove_tweet(3);
+        assert_eq!(ids(&timeline), [2]);
+        timeline.insert(vec![tweet(json!({ "id": 4 }))]);
+        assert_eq!(ids(&timeline), [4, 2]);
+    }
+
+    #[test]
+    fn every_tweet_of_a_long_thread_fits_with_its_number() {
+        for words in [60, 600, 6_000] {
+            let text = vec!["word"; words].join(" ");
+            let tweets = split_thread(&text, false);
+            let total = tweets.len();
+            for (i, tweet) in tweets.iter().enumerate() {
+                assert!(weighted_length(tweet) <= MAX_TWEET_LENGTH, "{:?}", tweet);
+                assert!(
+                    tweet.ends_with(&format!(" {}/{}", i + 1, total)),
+                    "{:?}",
+                    tweet
+                );
+            }
+        }
+        // A thread of a hundred or more needs more room than " 99/99".
+        let text = vec!["word"; 6_000].join(" ");
+        assert!(split_thread(&text, false).len() >= 100);
+    }
+
+    #[test]
+    fn tweets_posted_in_the_same_second_are_all_kept() {
+        let mut timeline = timeline(vec![
+            tweet(json!({ "id": 1, "full_text": "first" })),
+            tweet(json!({ "id": 2, "full_text": "second" })),
+        ]);
+        let ids: Vec<u64> = timeline.listed().map(|tweet| tweet.id).collect();
+        assert_eq!(ids, [2, 1]);
+        let drawn = render(&mut timeline, 60, 20);
+        assert!(drawn.contains("first"), "{}", drawn);
+        assert!(drawn.contains("second"), "{}", drawn);
+    }
+
+    #[test]
+    fn a_monochrome_theme_draws_without_colour() {
+        let mut config = Config::default();
+        config.theme.monochrome = Some(true);
+        let styles = Styles::from_config(&config).unwrap();
+        let author = styles.author(3);
+        assert!(author.add_modifier.contains(Modifier::BOLD));
+        assert_eq!(author.fg, None);
+
+        let mutes = Rc::new(Mutes::new(&Filters::default()).unwrap());
+        let mut timeline =
+            TimelineRenderer::offline(TimelineKind::Home, &Ui::default(), Rc::new(styles), mutes);
+        let mut author = user("author");
+        author["verified"] = json!(true);
+        timeline.insert(vec![tweet(json!({
+            "full_text": "colourless",
+            "retweet_count": 2,
+            "favorite_count": 3,
+            "favorited": true,
+            "user": author,
+        }))]);
+        timeline.unseen = 1;
+        let mut buf = Buffer::empty(Rect::new(0, 0, 60, 10));
+        timeline.render(buf.area, &mut buf);
+        for cell in &buf.content {
+            assert_eq!(
+                (cell.fg, cell.bg),
+                (Color::Reset, Color::Reset),
+                "{:?}",
+                cell
+            );
+        }
+    }
 }
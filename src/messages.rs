@@ -0,0 +1,152 @@
+//! Fetches direct messages.
+//!
+//! The endpoints egg-mode's `direct` module uses have been switched off, and
+//! it hasn't caught up with the events API that replaced them, so this calls
+//! that directly and reads the few fields twrs needs.
+
+use std::cmp::Reverse;
+
+use chrono::{DateTime, TimeZone, Utc};
+use egg_mode::{raw::ParamList, user::TwitterUser};
+use serde::Deserialize;
+
+/// Where the last 30 days of direct messages are listed, newest first.
+const EVENTS_URL: &str = "https://api.twitter.com/1.1/direct_messages/events/list.json";
+
+/// How many messages to ask for. This is the most Twitter allows at once.
+const COUNT: &str = "50";
+
+#[derive(Deserialize)]
+struct EventList {
+    events: Vec<Event>,
+}
+
+#[derive(Deserialize)]
+struct Event {
+    /// Milliseconds since the epoch, as a string.
+    created_timestamp: String,
+    /// Only set for events that are messages, which so far is all of them.
+    message_create: Option<MessageCreate>,
+}
+
+#[derive(Deserialize)]
+struct MessageCreate {
+    sender_id: String,
+    target: Target,
+    message_data: MessageData,
+}
+
+#[derive(Deserialize)]
+struct Target {
+    recipient_id: String,
+}
+
+#[derive(Deserialize)]
+struct MessageData {
+    text: String,
+    #[serde(default)]
+    entities: Entities,
+}
+
+#[derive(Default, Deserialize)]
+struct Entities {
+    #[serde(default)]
+    urls: Vec<Url>,
+}
+
+#[derive(Deserialize)]
+struct Url {
+    url: String,
+    display_url: String,
+}
+
+pub struct Message {
+    pub created_at: DateTime<Utc>,
+    /// Whether the message was sent to us, rather than by us.
+    pub received: bool,
+    /// The text, with t.co links swapped for the URLs they stand for.
+    pub text: String,
+}
+
+/// The messages between us and someone else.
+pub struct Conversation {
+    /// The id of whoever the conversation is with.
+    pub id: u64,
+    /// Who the conversation is with, if they could be looked up.
+    pub user: Option<TwitterUser>,
+    /// Newest first.
+    pub messages: Vec<Message>,
+}
+
+/// Fetches recent direct messages for the user `me`, the owner of `token`,
+/// grouped into conversations. The most recently active conversation comes
+/// first.
+pub async fn conversations(
+    me: u64,
+    token: &egg_mode::Token,
+) -> egg_mode::error::Result<Vec<Conversation>> {
+    let params = ParamList::new().add_param("count", COUNT);
+    let request = egg_mode::raw::request_get(EVENTS_URL, token, Some(&params));
+    let list: EventList = egg_mode::raw::response_json(request).await?.response;
+
+    let mut conversations: Vec<Conversation> = vec![];
+    for event in list.events {
+        let message = match event.message_create {
+            Some(message) => message,
+            None => continue,
+        };
+        let ids = (
+            message.sender_id.parse::<u64>(),
+            message.target.recipient_id.parse::<u64>(),
+            event.created_timestamp.parse::<i64>(),
+        );
+        let (sender, recipient, millis) = match ids {
+            (Ok(sender), Ok(recipient), Ok(millis)) => (sender, recipient, millis),
+            _ => continue,
+        };
+
+        let received = sender != me;
+        let with = if received { sender } else { recipient };
+        let mut text = message.message_data.text;
+        for url in &message.message_data.entities.urls {
+            text = text.replace(&url.url, &url.display_url);
+        }
+        let message = Message {
+            created_at: Utc.timestamp_millis(millis),
+            received,
+            text,
+        };
+
+        match conversations.iter_mut().find(|c| c.id == with) {
+            Some(conversation) => conversation.messages.push(message),
+            None => conversations.push(Conversation {
+                id: with,
+                user: None,
+                messages: vec![message],
+            }),
+        }
+    }
+
+    // Twitter lists the newest first already, but doesn't promise to.
+    for conversation in &mut conversations {
+        conversation
+            .messages
+            .sort_by_key(|message| Reverse(message.created_at));
+    }
+    conversations.sort_by_key(|conversation| Reverse(conversation.messages[0].created_at));
+
+    // Messages still make sense without names, so failing to look them up
+    // isn't worth failing over.
+    let ids: Vec<u64> = conversations.iter().map(|c| c.id).collect();
+    if !ids.is_empty() {
+        if let Ok(users) = egg_mode::user::lookup(ids, token).await {
+            for user in users.response {
+                if let Some(conversation) = conversations.iter_mut().find(|c| c.id == user.id) {
+                    conversation.user = Some(user);
+                }
+            }
+        }
+    }
+
+    Ok(conversations)
+}